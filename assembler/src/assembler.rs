@@ -8,13 +8,15 @@ use std::{
 
 use crate::{
     statements::{
-        Add, Ascii, Block, Branch, Call, Halt, Load, LoadEffectiveAddress, LoadImmediate, Return, Sleep, Statement,
-        StatementContainer, Store, Sub, Word,
+        Add, Ascii, Block, Branch, Call, CallRegister, DisableInterrupts, EnableInterrupts, ExtendedArithmetic, Halt,
+        Jump, JumpLong, Load, LoadEffectiveAddress, LoadImmediate, Pop, Push, Return, ReturnFromInterrupt, Sleep,
+        Statement, StatementContainer, Store, Sub, Word,
     },
     utils::encode_unsigned_integer,
 };
 
 use logos::Lexer;
+use shared::debug_info::{DebugInfo, FrameKind, SourceFrame, StatementInfo};
 
 use super::tokens::Token;
 
@@ -78,38 +80,35 @@ macro_rules! next_token_unwrapped {
 pub struct SourceLocation {
     file: String,
     character_span: Range<usize>,
+    kind: FrameKind,
 }
 
 impl PartialEq for SourceLocation {
     fn eq(&self, other: &Self) -> bool {
         return self.file == other.file && self.character_span == other.character_span;
     }
-
-    fn ne(&self, other: &Self) -> bool {
-        return !self.eq(other);
-    }
 }
 
 impl SourceLocation {
     pub fn as_human_readable_string(&self) -> String {
         let source = fs::read_to_string(Path::new(&self.file)).unwrap();
 
-        let lines: Vec<&str> = source.split("\n").collect();
+        let (line, column) = self.line_and_column(&source);
 
-        let mut line_counter = 0;
+        format!("{}:{}:{}", self.file, line, column)
+    }
+
+    /**
+     * Get the one based line and column of the start of this location given the source of the file it refers to
+     */
+    pub fn line_and_column(&self, source: &str) -> (usize, usize) {
         let mut char_counter = 0;
 
-        for line in lines {
+        for (line_counter, line) in source.split("\n").enumerate() {
             if char_counter + line.len() >= self.character_span.start {
-                return format!(
-                    "{}:{}:{}",
-                    self.file,
-                    line_counter + 1,
-                    (self.character_span.start - char_counter) + 1
-                );
+                return (line_counter + 1, (self.character_span.start - char_counter) + 1);
             }
 
-            line_counter += 1;
             char_counter += line.len() + 1;
         }
 
@@ -119,7 +118,11 @@ impl SourceLocation {
 
 impl SourceLocation {
     pub fn new(file: String, character_span: Range<usize>) -> SourceLocation {
-        SourceLocation { file, character_span }
+        SourceLocation {
+            file,
+            character_span,
+            kind: FrameKind::Statement,
+        }
     }
 }
 
@@ -154,6 +157,18 @@ impl ParsingContext {
         current_backtrace
     }
 
+    /**
+     * Get a backtrace for a macro invocation or file include, which is used as the base backtrace of the parsing
+     * context for the expanded source
+     */
+    pub fn get_expansion_backtrace(&self, local_span: Range<usize>, kind: FrameKind) -> Backtrace {
+        let mut backtrace = self.get_backtrace(local_span);
+
+        backtrace.last_mut().unwrap().kind = kind;
+
+        backtrace
+    }
+
     /**
      * A parsing context is recursive if the same source location appears more than once
      */
@@ -205,7 +220,12 @@ impl Display for AssemblerError {
     }
 }
 
-pub fn assemble(file: String) -> Result<Vec<u16>, AssemblerError> {
+pub struct AssembledProgram {
+    pub machine_code: Vec<u16>,
+    pub debug_info: DebugInfo,
+}
+
+pub fn assemble(file: String) -> Result<AssembledProgram, AssemblerError> {
     let mut label_map: HashMap<String, u16> = HashMap::new();
     let mut label_address = 0;
     let mut macros: HashMap<String, Macro> = HashMap::new();
@@ -214,7 +234,7 @@ pub fn assemble(file: String) -> Result<Vec<u16>, AssemblerError> {
 
     let parsing_context = ParsingContext::new(file.clone(), 0, Vec::new());
 
-    let statements = parse_file(
+    let mut statements = parse_file(
         file.clone(),
         &mut label_map,
         &mut subroutine_lookup_table_entries,
@@ -224,6 +244,8 @@ pub fn assemble(file: String) -> Result<Vec<u16>, AssemblerError> {
         parsing_context,
     )?;
 
+    relax_statements(&mut statements, &mut label_map);
+
     let mut out = Vec::new();
 
     out.append(&mut build_slt(&label_map, &subroutine_lookup_table_entries)?);
@@ -231,7 +253,7 @@ pub fn assemble(file: String) -> Result<Vec<u16>, AssemblerError> {
     // Note - this isn't an absolute address but is instead relative to the end of the SLT
     let mut statement_address = 0;
 
-    for statement in statements {
+    for statement in statements.iter() {
         out.append(
             statement
                 .assemble(statement_address, &label_map, &subroutine_lookup_table_entries)?
@@ -240,7 +262,60 @@ pub fn assemble(file: String) -> Result<Vec<u16>, AssemblerError> {
         statement_address += statement.width();
     }
 
-    return Ok(out);
+    let debug_info = build_debug_info(
+        &statements,
+        &label_map,
+        subroutine_lookup_table_entries.len() as u16 + 1,
+    )?;
+
+    return Ok(AssembledProgram {
+        machine_code: out,
+        debug_info,
+    });
+}
+
+/**
+ * Widen statements whose labels are out of range (see `Statement::relax`) and move the labels after them, repeating
+ * until every label is in range, since moving labels can put others out of range
+ */
+fn relax_statements(statements: &mut [StatementContainer<dyn Statement>], label_map: &mut HashMap<String, u16>) {
+    // Each label stays in front of the same statement, the first one at or after its address. Any statements between
+    // the label and that one are empty, so are never widened.
+    let addresses = statement_addresses(statements);
+
+    let label_statements: Vec<(String, usize)> = label_map
+        .iter()
+        .map(|(label, address)| (label.clone(), addresses.partition_point(|start| start < address)))
+        .collect();
+
+    loop {
+        let addresses = statement_addresses(statements);
+
+        for (label, index) in &label_statements {
+            label_map.insert(label.clone(), addresses[*index]);
+        }
+
+        let mut changed = false;
+
+        for (statement, address) in statements.iter_mut().zip(addresses) {
+            changed |= statement.relax(address, label_map);
+        }
+
+        if !changed {
+            return;
+        }
+    }
+}
+
+// The address of each statement relative to the end of the SLT, followed by the address after the last statement
+fn statement_addresses(statements: &[StatementContainer<dyn Statement>]) -> Vec<u16> {
+    let mut addresses = vec![0];
+
+    for statement in statements {
+        addresses.push(addresses.last().unwrap() + statement.width());
+    }
+
+    addresses
 }
 
 fn parse_file(
@@ -258,7 +333,7 @@ fn parse_file(
         Ok(source) => source,
         Err(e) => {
             return Err(AssemblerError::new(
-                format!("Unable to read file {}: {}", file, e.to_string()),
+                format!("Unable to read file {}: {}", file, e),
                 parsing_context.backtrace,
             ))
         }
@@ -313,7 +388,7 @@ fn parse_file(
 
                 if number_of_params < 0 {
                     return Err(AssemblerError::new(
-                        format!("Number of arguments for a macro must be greater than zero"),
+                        "Number of arguments for a macro must be greater than zero".to_string(),
                         parsing_context.get_backtrace(lexer.span()),
                     ));
                 }
@@ -381,6 +456,43 @@ fn parse_statement(
         // Instructions
         "ADD" => Some(Box::new(parse_add_statement(lexer, parsing_context)?)),
         "SUB" => Some(Box::new(parse_sub_statement(lexer, parsing_context)?)),
+        "ADC" => Some(Box::new(parse_add_with_carry_statement(lexer, parsing_context)?)),
+        "SBC" => Some(Box::new(parse_subtract_with_carry_statement(lexer, parsing_context)?)),
+        "MUL" => Some(Box::new(parse_extended_arithmetic_statement(
+            0b000,
+            lexer,
+            parsing_context,
+        )?)),
+        "DIVU" => Some(Box::new(parse_extended_arithmetic_statement(
+            0b001,
+            lexer,
+            parsing_context,
+        )?)),
+        "MODU" => Some(Box::new(parse_extended_arithmetic_statement(
+            0b010,
+            lexer,
+            parsing_context,
+        )?)),
+        "DIVS" => Some(Box::new(parse_extended_arithmetic_statement(
+            0b011,
+            lexer,
+            parsing_context,
+        )?)),
+        "MODS" => Some(Box::new(parse_extended_arithmetic_statement(
+            0b100,
+            lexer,
+            parsing_context,
+        )?)),
+        "XOR" => Some(Box::new(parse_extended_arithmetic_statement(
+            0b101,
+            lexer,
+            parsing_context,
+        )?)),
+        "OR" => Some(Box::new(parse_extended_arithmetic_statement(
+            0b110,
+            lexer,
+            parsing_context,
+        )?)),
         "LEA" => Some(Box::new(parse_load_effective_address_statement(
             lexer,
             parsing_context,
@@ -397,6 +509,14 @@ fn parse_statement(
         "RET" => Some(Box::new(parse_return_statement(lexer, parsing_context)?)),
         "HLT" => Some(Box::new(parse_halt_statement(lexer, parsing_context)?)),
         "SLP" => Some(Box::new(parse_sleep_statement(lexer, parsing_context)?)),
+        "RTI" => Some(Box::new(parse_return_from_interrupt_statement(lexer, parsing_context)?)),
+        "EI" => Some(Box::new(parse_enable_interrupts_statement(lexer, parsing_context)?)),
+        "DI" => Some(Box::new(parse_disable_interrupts_statement(lexer, parsing_context)?)),
+        "PUSH" => Some(Box::new(parse_push_statement(lexer, parsing_context)?)),
+        "POP" => Some(Box::new(parse_pop_statement(lexer, parsing_context)?)),
+        "JMP" => Some(Box::new(parse_jump_statement(lexer, parsing_context)?)),
+        "CALLR" => Some(Box::new(parse_call_register_statement(lexer, parsing_context)?)),
+        "JMPL" => Some(Box::new(parse_jump_long_statement(lexer, parsing_context)?)),
 
         // Directives
         "WORD" => Some(Box::new(parse_word_statement(lexer, parsing_context)?)),
@@ -493,6 +613,54 @@ fn parse_sub_statement(lexer: &mut Lexer<Token>, parsing_context: &ParsingContex
     Ok(Sub::new(destination_register, source_register_zero, source_one_value))
 }
 
+// Adding and subtracting with carry only take registers, the low bit of the register form selects the carry
+fn parse_add_with_carry_statement(
+    lexer: &mut Lexer<Token>,
+    parsing_context: &ParsingContext,
+) -> Result<Add, AssemblerError> {
+    let destination_register = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+    let source_register_zero = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+    let source_register_one = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+
+    Ok(Add::new(
+        destination_register,
+        source_register_zero,
+        (1 << 5) | (source_register_one << 2) | 1,
+    ))
+}
+
+fn parse_subtract_with_carry_statement(
+    lexer: &mut Lexer<Token>,
+    parsing_context: &ParsingContext,
+) -> Result<Sub, AssemblerError> {
+    let destination_register = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+    let source_register_zero = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+    let source_register_one = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+
+    Ok(Sub::new(
+        destination_register,
+        source_register_zero,
+        (1 << 5) | (source_register_one << 2) | 1,
+    ))
+}
+
+fn parse_extended_arithmetic_statement(
+    operation: u16,
+    lexer: &mut Lexer<Token>,
+    parsing_context: &ParsingContext,
+) -> Result<ExtendedArithmetic, AssemblerError> {
+    let destination_register = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+    let source_register_zero = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+    let source_register_one = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+
+    Ok(ExtendedArithmetic::new(
+        operation,
+        destination_register,
+        source_register_zero,
+        source_register_one,
+    ))
+}
+
 fn parse_load_effective_address_statement(
     lexer: &mut Lexer<Token>,
     parsing_context: &ParsingContext,
@@ -575,6 +743,63 @@ fn parse_halt_statement<'a>(_: &mut Lexer<'a, Token>, _: &ParsingContext) -> Res
     Ok(Halt::new())
 }
 
+fn parse_return_from_interrupt_statement<'a>(
+    _: &mut Lexer<'a, Token>,
+    _: &ParsingContext,
+) -> Result<ReturnFromInterrupt, AssemblerError> {
+    Ok(ReturnFromInterrupt::new())
+}
+
+fn parse_enable_interrupts_statement<'a>(
+    _: &mut Lexer<'a, Token>,
+    _: &ParsingContext,
+) -> Result<EnableInterrupts, AssemblerError> {
+    Ok(EnableInterrupts::new())
+}
+
+fn parse_disable_interrupts_statement<'a>(
+    _: &mut Lexer<'a, Token>,
+    _: &ParsingContext,
+) -> Result<DisableInterrupts, AssemblerError> {
+    Ok(DisableInterrupts::new())
+}
+
+fn parse_push_statement(lexer: &mut Lexer<Token>, parsing_context: &ParsingContext) -> Result<Push, AssemblerError> {
+    let source_register = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+
+    Ok(Push::new(source_register))
+}
+
+fn parse_pop_statement(lexer: &mut Lexer<Token>, parsing_context: &ParsingContext) -> Result<Pop, AssemblerError> {
+    let destination_register = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+
+    Ok(Pop::new(destination_register))
+}
+
+fn parse_jump_statement(lexer: &mut Lexer<Token>, parsing_context: &ParsingContext) -> Result<Jump, AssemblerError> {
+    let base_register = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+
+    Ok(Jump::new(base_register))
+}
+
+fn parse_jump_long_statement(
+    lexer: &mut Lexer<Token>,
+    parsing_context: &ParsingContext,
+) -> Result<JumpLong, AssemblerError> {
+    let label = next_token_unwrapped!(lexer, parsing_context, Token::Label)?;
+
+    Ok(JumpLong::new(label))
+}
+
+fn parse_call_register_statement(
+    lexer: &mut Lexer<Token>,
+    parsing_context: &ParsingContext,
+) -> Result<CallRegister, AssemblerError> {
+    let base_register = next_token_unwrapped!(lexer, parsing_context, Token::Register)?;
+
+    Ok(CallRegister::new(base_register))
+}
+
 fn parse_sleep_statement(lexer: &mut Lexer<Token>, parsing_context: &ParsingContext) -> Result<Sleep, AssemblerError> {
     let duration = next_token_unwrapped!(lexer, parsing_context, Token::NumericLiteral)?;
 
@@ -587,14 +812,14 @@ fn parse_sleep_statement(lexer: &mut Lexer<Token>, parsing_context: &ParsingCont
 }
 
 fn parse_word_statement(lexer: &mut Lexer<Token>, parsing_context: &ParsingContext) -> Result<Word, AssemblerError> {
-    let numeric_literal = next_token_unwrapped!(lexer, parsing_context, Token::NumericLiteral)?;
-
-    let encoded_value = match encode_unsigned_integer(numeric_literal, 16) {
-        Ok(value) => value,
-        Err(e) => return Err(AssemblerError::new(e, parsing_context.get_backtrace(lexer.span()))),
-    };
-
-    Ok(Word::new(encoded_value))
+    match next_token!(lexer, parsing_context, Token::NumericLiteral, Token::Label)? {
+        Token::NumericLiteral(numeric_literal) => match encode_unsigned_integer(numeric_literal, 16) {
+            Ok(value) => Ok(Word::new(value)),
+            Err(e) => Err(AssemblerError::new(e, parsing_context.get_backtrace(lexer.span()))),
+        },
+        Token::Label(label) => Ok(Word::from_label(label)),
+        _ => unreachable!(),
+    }
 }
 
 fn parse_ascii_statement(lexer: &mut Lexer<Token>, parsing_context: &ParsingContext) -> Result<Ascii, AssemblerError> {
@@ -645,7 +870,7 @@ fn parse_include_statement(
     let included_file_parsing_context = ParsingContext::new(
         file_path.clone(),
         0,
-        parsing_context.get_backtrace(include_statement_start..lexer.span().end),
+        parsing_context.get_expansion_backtrace(include_statement_start..lexer.span().end, FrameKind::Include),
     );
 
     if included_file_parsing_context.is_recursive() {
@@ -679,7 +904,7 @@ fn parse_macro_invocation(
     let macro_parsing_context = ParsingContext::new(
         r#macro.definition_file.clone(),
         r#macro.definition_offset,
-        parsing_context.get_backtrace(lexer.span()),
+        parsing_context.get_expansion_backtrace(lexer.span(), FrameKind::MacroInvocation),
     );
 
     if macro_parsing_context.is_recursive() {
@@ -768,3 +993,68 @@ fn build_slt(
 
     Ok(out)
 }
+
+/**
+ * Build the debug info for a program, mapping the address of each statement back to its source locations
+ *
+ * `program_offset` is the absolute address of the first statement (i.e. the size of the SLT)
+ */
+fn build_debug_info(
+    statements: &[StatementContainer<dyn Statement>],
+    label_map: &HashMap<String, u16>,
+    program_offset: u16,
+) -> Result<DebugInfo, AssemblerError> {
+    let mut debug_info = DebugInfo::new();
+    let mut sources: HashMap<String, String> = HashMap::new();
+
+    let mut labels: Vec<(String, u16)> = label_map
+        .iter()
+        .map(|(label, address)| (label.clone(), address + program_offset))
+        .collect();
+
+    labels.sort_by(|(label_a, address_a), (label_b, address_b)| address_a.cmp(address_b).then(label_a.cmp(label_b)));
+
+    debug_info.labels = labels;
+
+    let mut statement_address = program_offset;
+
+    for statement in statements {
+        let mut backtrace = Vec::new();
+
+        for source_location in statement.backtrace() {
+            if !sources.contains_key(&source_location.file) {
+                let source = match fs::read_to_string(Path::new(&source_location.file)) {
+                    Ok(source) => source,
+                    Err(e) => {
+                        return Err(AssemblerError::new(
+                            format!("Unable to read file {}: {}", source_location.file, e),
+                            statement.backtrace().clone(),
+                        ))
+                    }
+                };
+
+                sources.insert(source_location.file.clone(), source);
+            }
+
+            let (line, column) = source_location.line_and_column(&sources[&source_location.file]);
+
+            backtrace.push(SourceFrame {
+                file: debug_info.file_index(&source_location.file),
+                line,
+                column,
+                kind: source_location.kind,
+            });
+        }
+
+        debug_info.statements.push(StatementInfo {
+            address: statement_address,
+            width: statement.width(),
+            is_instruction: statement.is_instruction(),
+            backtrace,
+        });
+
+        statement_address += statement.width();
+    }
+
+    Ok(debug_info)
+}
@@ -0,0 +1,15 @@
+// The assembler returns explicitly, spells out zero opcode fields (e.g. `0b0000 << 12`), threads the parser's state
+// through function arguments and passes the SLT as `&Vec<String>`
+#![allow(
+    clippy::needless_return,
+    clippy::identity_op,
+    clippy::too_many_arguments,
+    clippy::ptr_arg
+)]
+
+mod assembler;
+mod statements;
+mod tokens;
+mod utils;
+
+pub use assembler::{assemble, AssembledProgram, AssemblerError, Backtrace, SourceLocation};
@@ -5,14 +5,20 @@ use std::{
 
 use assembler::assemble;
 
-mod assembler;
-mod statements;
-mod tokens;
-mod utils;
-
 fn main() {
-    let input_path = std::env::args().nth(1).expect("No input path provided");
-    let output_path = std::env::args().nth(2).expect("No output path provided");
+    let mut args = std::env::args().skip(1);
+    let mut positional_args = Vec::new();
+    let mut debug_info_path: Option<String> = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--debug-info" => debug_info_path = Some(args.next().expect("No debug info path provided")),
+            _ => positional_args.push(arg),
+        }
+    }
+
+    let input_path = positional_args.first().expect("No input path provided");
+    let output_path = positional_args.get(1).expect("No output path provided");
 
     let absolute_input_path = absolute(Path::new(input_path.as_str()))
         .unwrap()
@@ -21,15 +27,19 @@ fn main() {
         .to_string();
 
     match assemble(absolute_input_path) {
-        Ok(machine_code) => {
+        Ok(program) => {
             let mut bytes: Vec<u8> = Vec::new();
 
-            for word in machine_code {
+            for word in program.machine_code {
                 bytes.push((word >> 8 & 0xFF) as u8);
                 bytes.push((word & 0xFF) as u8)
             }
 
             fs::write(output_path, bytes).unwrap();
+
+            if let Some(debug_info_path) = debug_info_path {
+                fs::write(debug_info_path, program.debug_info.to_string()).unwrap();
+            }
         }
         // TODO: Improve error print out
         Err(err) => print!("{}", err),
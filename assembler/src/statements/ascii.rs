@@ -26,7 +26,7 @@ impl Statement for Ascii {
     ) -> Result<Vec<u16>, AssemblerError> {
         let mut out: Vec<u16> = self.value.as_bytes().iter().map(|byte| *byte as u16).collect();
 
-        out.extend(vec![0 as u16]);
+        out.extend(vec![0_u16]);
 
         return Ok(out);
     }
@@ -34,4 +34,8 @@ impl Statement for Ascii {
     fn width(&self) -> u16 {
         return self.value.len() as u16 + 1;
     }
+
+    fn is_instruction(&self) -> bool {
+        return false;
+    }
 }
@@ -28,4 +28,8 @@ impl Statement for Block {
     fn width(&self) -> u16 {
         return self.size;
     }
+
+    fn is_instruction(&self) -> bool {
+        return false;
+    }
 }
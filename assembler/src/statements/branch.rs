@@ -2,10 +2,12 @@ use std::collections::HashMap;
 
 use crate::{
     assembler::{AssemblerError, Backtrace},
-    utils::{encode_signed_integer, get_encoded_label_offset},
+    utils::{encode_signed_integer, get_absolute_label_address, get_encoded_label_offset, is_label_in_range},
 };
 
-use super::Statement;
+use super::{jump_long::JUMP_LONG, Statement};
+
+const NZP: u16 = 0b111;
 
 #[derive(Clone)]
 enum LabelOrOffset {
@@ -16,6 +18,8 @@ enum LabelOrOffset {
 pub struct Branch {
     conditions: u16,
     label_or_offset: LabelOrOffset,
+    // Whether the label is out of range, so the branch is assembled as a JMPL to it instead
+    relaxed: bool,
 }
 
 impl Branch {
@@ -23,6 +27,7 @@ impl Branch {
         Branch {
             conditions,
             label_or_offset: LabelOrOffset::Offset(offset),
+            relaxed: false,
         }
     }
 
@@ -30,6 +35,41 @@ impl Branch {
         Branch {
             conditions,
             label_or_offset: LabelOrOffset::Label(label),
+            relaxed: false,
+        }
+    }
+
+    // The number of bits of the offset, branches on c and v leave n, z and p clear and put their conditions in the top
+    // two bits of the offset
+    fn offset_bits(&self) -> u16 {
+        match self.conditions & NZP {
+            0 => 7,
+            _ => 9,
+        }
+    }
+
+    fn encode(conditions: u16, offset: u16) -> u16 {
+        match conditions & NZP {
+            0 => (0b1001 << 12) | ((conditions >> 3) << 7) | offset,
+            nzp => (0b1001 << 12) | (nzp << 9) | offset,
+        }
+    }
+
+    /**
+     * A branch to a label which is out of range: a JMPL if the branch is unconditional, otherwise a branch on the
+     * opposite conditions over a JMPL. Branches on c and v can't be inverted, so they branch to the JMPL instead and
+     * otherwise skip it.
+     */
+    fn assemble_relaxed(&self, target: u16) -> Vec<u16> {
+        match self.conditions & NZP {
+            NZP => vec![JUMP_LONG, target],
+            0 => vec![
+                Branch::encode(self.conditions, 1),
+                Branch::encode(NZP, 2),
+                JUMP_LONG,
+                target,
+            ],
+            nzp => vec![Branch::encode(!nzp & NZP, 2), JUMP_LONG, target],
         }
     }
 }
@@ -39,12 +79,19 @@ impl Statement for Branch {
         &self,
         address: u16,
         label_map: &HashMap<String, u16>,
-        _: &Vec<String>,
+        subroutine_lookup_table_entries: &Vec<String>,
         backtrace: &Backtrace,
     ) -> Result<Vec<u16>, AssemblerError> {
+        if let (true, LabelOrOffset::Label(label)) = (self.relaxed, &self.label_or_offset) {
+            return match get_absolute_label_address(label, label_map, subroutine_lookup_table_entries) {
+                Ok(target) => Ok(self.assemble_relaxed(target)),
+                Err(e) => Err(AssemblerError::new(e, backtrace.clone())),
+            };
+        }
+
         let encoded_offset_result = match self.label_or_offset.clone() {
-            LabelOrOffset::Offset(offset) => encode_signed_integer(offset, 9),
-            LabelOrOffset::Label(label) => get_encoded_label_offset(address + 1, &label, label_map, 9),
+            LabelOrOffset::Offset(offset) => encode_signed_integer(offset, self.offset_bits() as u32),
+            LabelOrOffset::Label(label) => get_encoded_label_offset(address + 1, &label, label_map, self.offset_bits()),
         };
 
         let encoded_offset = match encoded_offset_result {
@@ -52,10 +99,32 @@ impl Statement for Branch {
             Err(e) => return Err(AssemblerError::new(e, backtrace.clone())),
         };
 
-        return Ok(vec![(0b1001 << 12) | (self.conditions << 9) | encoded_offset]);
+        return Ok(vec![Branch::encode(self.conditions, encoded_offset)]);
     }
 
     fn width(&self) -> u16 {
-        return 1;
+        if !self.relaxed {
+            return 1;
+        }
+
+        match self.conditions & NZP {
+            NZP => return 2,
+            0 => return 4,
+            _ => return 3,
+        }
+    }
+
+    fn relax(&mut self, address: u16, label_map: &HashMap<String, u16>) -> bool {
+        let LabelOrOffset::Label(label) = &self.label_or_offset else {
+            return false;
+        };
+
+        if self.relaxed || is_label_in_range(address + 1, label, label_map, self.offset_bits()) {
+            return false;
+        }
+
+        self.relaxed = true;
+
+        return true;
     }
 }
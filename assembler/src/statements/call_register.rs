@@ -0,0 +1,31 @@
+use std::collections::HashMap;
+
+use crate::assembler::{AssemblerError, Backtrace};
+
+use super::Statement;
+
+pub struct CallRegister {
+    base_register: u16,
+}
+
+impl CallRegister {
+    pub fn new(base_register: u16) -> CallRegister {
+        CallRegister { base_register }
+    }
+}
+
+impl Statement for CallRegister {
+    fn assemble(
+        &self,
+        _: u16,
+        _: &HashMap<String, u16>,
+        _: &Vec<String>,
+        _: &Backtrace,
+    ) -> Result<Vec<u16>, AssemblerError> {
+        return Ok(vec![0b1110011000000000 | self.base_register]);
+    }
+
+    fn width(&self) -> u16 {
+        return 1;
+    }
+}
@@ -0,0 +1,29 @@
+use std::collections::HashMap;
+
+use crate::assembler::{AssemblerError, Backtrace};
+
+use super::Statement;
+
+pub struct DisableInterrupts {}
+
+impl DisableInterrupts {
+    pub fn new() -> DisableInterrupts {
+        DisableInterrupts {}
+    }
+}
+
+impl Statement for DisableInterrupts {
+    fn assemble(
+        &self,
+        _: u16,
+        _: &HashMap<String, u16>,
+        _: &Vec<String>,
+        _: &Backtrace,
+    ) -> Result<Vec<u16>, AssemblerError> {
+        return Ok(vec![0b1110001000000000]);
+    }
+
+    fn width(&self) -> u16 {
+        return 1;
+    }
+}
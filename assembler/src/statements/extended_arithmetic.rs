@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use crate::assembler::{AssemblerError, Backtrace};
+
+use super::Statement;
+
+pub struct ExtendedArithmetic {
+    // The low three bits of the instruction, selecting the operation within the group
+    operation: u16,
+    destination_register: u16,
+    source_register_zero: u16,
+    source_register_one: u16,
+}
+
+impl ExtendedArithmetic {
+    pub fn new(
+        operation: u16,
+        destination_register: u16,
+        source_register_zero: u16,
+        source_register_one: u16,
+    ) -> ExtendedArithmetic {
+        ExtendedArithmetic {
+            operation,
+            destination_register,
+            source_register_zero,
+            source_register_one,
+        }
+    }
+}
+
+impl Statement for ExtendedArithmetic {
+    fn assemble(
+        &self,
+        _: u16,
+        _: &HashMap<String, u16>,
+        _: &Vec<String>,
+        _: &Backtrace,
+    ) -> Result<Vec<u16>, AssemblerError> {
+        return Ok(vec![
+            (0b1111 << 12)
+                | (self.destination_register << 9)
+                | (self.source_register_zero << 6)
+                | (self.source_register_one << 3)
+                | self.operation,
+        ]);
+    }
+
+    fn width(&self) -> u16 {
+        return 1;
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::HashMap;
+
+use crate::{
+    assembler::{AssemblerError, Backtrace},
+    utils::get_absolute_label_address,
+};
+
+use super::Statement;
+
+// JMPL is followed by the address to jump to
+pub const JUMP_LONG: u16 = 0b1110011100000000;
+
+pub struct JumpLong {
+    label: String,
+}
+
+impl JumpLong {
+    pub fn new(label: String) -> JumpLong {
+        JumpLong { label }
+    }
+}
+
+impl Statement for JumpLong {
+    fn assemble(
+        &self,
+        _: u16,
+        label_map: &HashMap<String, u16>,
+        subroutine_lookup_table_entries: &Vec<String>,
+        backtrace: &Backtrace,
+    ) -> Result<Vec<u16>, AssemblerError> {
+        match get_absolute_label_address(&self.label, label_map, subroutine_lookup_table_entries) {
+            Ok(address) => return Ok(vec![JUMP_LONG, address]),
+            Err(e) => return Err(AssemblerError::new(e, backtrace.clone())),
+        }
+    }
+
+    fn width(&self) -> u16 {
+        return 2;
+    }
+}
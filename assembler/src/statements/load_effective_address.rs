@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use crate::{
     assembler::{AssemblerError, Backtrace},
-    utils::{encode_signed_integer, get_encoded_label_offset},
+    utils::{encode_signed_integer, get_absolute_label_address, get_encoded_label_offset, is_label_in_range},
 };
 
 use super::Statement;
@@ -16,6 +16,8 @@ enum LabelOrOffset {
 pub struct LoadEffectiveAddress {
     destination_register: u16,
     label_or_offset: LabelOrOffset,
+    // Whether the label is out of range, so its address is loaded from a word stored after the instruction instead
+    relaxed: bool,
 }
 
 impl LoadEffectiveAddress {
@@ -23,6 +25,7 @@ impl LoadEffectiveAddress {
         LoadEffectiveAddress {
             destination_register,
             label_or_offset: LabelOrOffset::Offset(offset),
+            relaxed: false,
         }
     }
 
@@ -30,6 +33,7 @@ impl LoadEffectiveAddress {
         LoadEffectiveAddress {
             destination_register,
             label_or_offset: LabelOrOffset::Label(label),
+            relaxed: false,
         }
     }
 }
@@ -39,9 +43,24 @@ impl Statement for LoadEffectiveAddress {
         &self,
         address: u16,
         label_map: &HashMap<String, u16>,
-        _: &Vec<String>,
+        subroutine_lookup_table_entries: &Vec<String>,
         backtrace: &Backtrace,
     ) -> Result<Vec<u16>, AssemblerError> {
+        if let (true, LabelOrOffset::Label(label)) = (self.relaxed, &self.label_or_offset) {
+            let target = match get_absolute_label_address(label, label_map, subroutine_lookup_table_entries) {
+                Ok(target) => target,
+                Err(e) => return Err(AssemblerError::new(e, backtrace.clone())),
+            };
+
+            // LEA DR #3, LD DR DR #0, BR nzp #1 (over the address), then the address
+            return Ok(vec![
+                (0b0101 << 12) | (self.destination_register << 9) | 3,
+                (0b0110 << 12) | (self.destination_register << 9) | (self.destination_register << 6),
+                (0b1001 << 12) | (0b111 << 9) | 1,
+                target,
+            ]);
+        }
+
         let encoded_offset_result = match self.label_or_offset.clone() {
             LabelOrOffset::Offset(offset) => encode_signed_integer(offset, 9),
             LabelOrOffset::Label(label) => get_encoded_label_offset(address, &label, label_map, 9),
@@ -56,6 +75,23 @@ impl Statement for LoadEffectiveAddress {
     }
 
     fn width(&self) -> u16 {
-        return 1;
+        match self.relaxed {
+            true => return 4,
+            false => return 1,
+        }
+    }
+
+    fn relax(&mut self, address: u16, label_map: &HashMap<String, u16>) -> bool {
+        let LabelOrOffset::Label(label) = &self.label_or_offset else {
+            return false;
+        };
+
+        if self.relaxed || is_label_in_range(address, label, label_map, 9) {
+            return false;
+        }
+
+        self.relaxed = true;
+
+        return true;
     }
 }
@@ -3,11 +3,20 @@ mod ascii;
 mod block;
 mod branch;
 mod call;
+mod call_register;
+mod disable_interrupts;
+mod enable_interrupts;
+mod extended_arithmetic;
 mod halt;
+mod jump;
+mod jump_long;
 mod load;
 mod load_effective_address;
 mod load_immediate;
+mod pop;
+mod push;
 mod r#return;
+mod return_from_interrupt;
 mod sleep;
 mod store;
 mod sub;
@@ -18,11 +27,20 @@ pub use ascii::Ascii;
 pub use block::Block;
 pub use branch::Branch;
 pub use call::Call;
+pub use call_register::CallRegister;
+pub use disable_interrupts::DisableInterrupts;
+pub use enable_interrupts::EnableInterrupts;
+pub use extended_arithmetic::ExtendedArithmetic;
 pub use halt::Halt;
+pub use jump::Jump;
+pub use jump_long::JumpLong;
 pub use load::Load;
 pub use load_effective_address::LoadEffectiveAddress;
 pub use load_immediate::LoadImmediate;
+pub use pop::Pop;
+pub use push::Push;
 pub use r#return::Return;
+pub use return_from_interrupt::ReturnFromInterrupt;
 pub use sleep::Sleep;
 pub use store::Store;
 pub use sub::Sub;
@@ -41,6 +59,21 @@ pub trait Statement {
         span: &Backtrace,
     ) -> Result<Vec<u16>, AssemblerError>;
     fn width(&self) -> u16;
+
+    /**
+     * Switch to a longer encoding if a label the statement refers to is out of range from its address, returning
+     * whether its width changed. Statements only ever get wider, so laying out the program converges.
+     */
+    fn relax(&mut self, _address: u16, _label_map: &HashMap<String, u16>) -> bool {
+        false
+    }
+
+    /**
+     * Whether the statement emits executable instructions rather than data
+     */
+    fn is_instruction(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Clone)]
@@ -67,4 +100,16 @@ impl StatementContainer<dyn Statement> {
     pub fn width(&self) -> u16 {
         self.statement.width()
     }
+
+    pub fn relax(&mut self, address: u16, label_map: &HashMap<String, u16>) -> bool {
+        self.statement.relax(address, label_map)
+    }
+
+    pub fn is_instruction(&self) -> bool {
+        self.statement.is_instruction()
+    }
+
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
 }
@@ -0,0 +1,31 @@
+use std::collections::HashMap;
+
+use crate::assembler::{AssemblerError, Backtrace};
+
+use super::Statement;
+
+pub struct Pop {
+    destination_register: u16,
+}
+
+impl Pop {
+    pub fn new(destination_register: u16) -> Pop {
+        Pop { destination_register }
+    }
+}
+
+impl Statement for Pop {
+    fn assemble(
+        &self,
+        _: u16,
+        _: &HashMap<String, u16>,
+        _: &Vec<String>,
+        _: &Backtrace,
+    ) -> Result<Vec<u16>, AssemblerError> {
+        return Ok(vec![0b1110010000000000 | self.destination_register]);
+    }
+
+    fn width(&self) -> u16 {
+        return 1;
+    }
+}
@@ -0,0 +1,31 @@
+use std::collections::HashMap;
+
+use crate::assembler::{AssemblerError, Backtrace};
+
+use super::Statement;
+
+pub struct Push {
+    source_register: u16,
+}
+
+impl Push {
+    pub fn new(source_register: u16) -> Push {
+        Push { source_register }
+    }
+}
+
+impl Statement for Push {
+    fn assemble(
+        &self,
+        _: u16,
+        _: &HashMap<String, u16>,
+        _: &Vec<String>,
+        _: &Backtrace,
+    ) -> Result<Vec<u16>, AssemblerError> {
+        return Ok(vec![0b1110001100000000 | self.source_register]);
+    }
+
+    fn width(&self) -> u16 {
+        return 1;
+    }
+}
@@ -1,16 +1,33 @@
 use std::collections::HashMap;
 
-use crate::assembler::{AssemblerError, Backtrace};
+use crate::{
+    assembler::{AssemblerError, Backtrace},
+    utils::get_absolute_label_address,
+};
 
 use super::Statement;
 
+enum WordValue {
+    Literal(u16),
+    // The absolute address of a label, e.g. for a table of addresses to JMP or CALLR to
+    Label(String),
+}
+
 pub struct Word {
-    value: u16,
+    value: WordValue,
 }
 
 impl Word {
     pub fn new(value: u16) -> Word {
-        Word { value }
+        Word {
+            value: WordValue::Literal(value),
+        }
+    }
+
+    pub fn from_label(label: String) -> Word {
+        Word {
+            value: WordValue::Label(label),
+        }
     }
 }
 
@@ -18,14 +35,26 @@ impl Statement for Word {
     fn assemble(
         &self,
         _: u16,
-        _: &HashMap<String, u16>,
-        _: &Vec<String>,
-        _: &Backtrace,
+        label_map: &HashMap<String, u16>,
+        subroutine_lookup_table_entries: &Vec<String>,
+        backtrace: &Backtrace,
     ) -> Result<Vec<u16>, AssemblerError> {
-        return Ok(vec![self.value]);
+        match &self.value {
+            WordValue::Literal(value) => return Ok(vec![*value]),
+            WordValue::Label(label) => {
+                match get_absolute_label_address(label, label_map, subroutine_lookup_table_entries) {
+                    Ok(address) => return Ok(vec![address]),
+                    Err(e) => return Err(AssemblerError::new(e, backtrace.clone())),
+                }
+            }
+        }
     }
 
     fn width(&self) -> u16 {
         return 1;
     }
+
+    fn is_instruction(&self) -> bool {
+        return false;
+    }
 }
@@ -39,6 +39,14 @@ fn branch_conditions_callback(lexer: &mut Lexer<Token>) -> BranchConditions {
         out |= BranchConditions::POSITIVE;
     }
 
+    if lexer.slice().contains('c') {
+        out |= BranchConditions::CARRY;
+    }
+
+    if lexer.slice().contains('v') {
+        out |= BranchConditions::OVERFLOW;
+    }
+
     return out;
 }
 
@@ -77,7 +85,8 @@ pub enum Token {
     #[regex("R[0-7]", register_callback)]
     Register(u16),
 
-    #[regex("nzp|nz|np|n|zp|z|p", branch_conditions_callback)]
+    // n, z and p can't be combined with c and v, which are encoded differently
+    #[regex("nzp|nz|np|n|zp|z|p|cv|c|v", branch_conditions_callback)]
     BranchConditons(BranchConditions),
 
     #[regex("\\.[A-z0-9]+", label_callback)]
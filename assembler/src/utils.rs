@@ -1,11 +1,11 @@
 use std::collections::HashMap;
 
 pub fn encode_signed_integer(integer: i32, bits: u32) -> Result<u16, String> {
-    let min_value = -(2 as i32).pow(bits - 1);
-    let max_value = (2 as i32).pow(bits - 1) - 1;
+    let min_value = -2_i32.pow(bits - 1);
+    let max_value = 2_i32.pow(bits - 1) - 1;
 
     match integer >= min_value && integer <= max_value {
-        true => Ok(integer as u16 & ((2 as u32).pow(bits) - 1) as u16),
+        true => Ok(integer as u16 & (2_u32.pow(bits) - 1) as u16),
         false => Err(format!(
             "Invalid value for i{} \"{}\", values should be in range {}-{}",
             bits, integer, min_value, max_value
@@ -15,7 +15,7 @@ pub fn encode_signed_integer(integer: i32, bits: u32) -> Result<u16, String> {
 
 pub fn encode_unsigned_integer(integer: i32, bits: u32) -> Result<u16, String> {
     let min_value = 0;
-    let max_value = (2 as i32).pow(bits) - 1;
+    let max_value = 2_i32.pow(bits) - 1;
 
     match integer >= min_value && integer <= max_value {
         true => Ok(integer as u16),
@@ -40,11 +40,40 @@ pub fn get_encoded_label_offset(
             match encode_signed_integer((*label_address as i32) - (address as i32), bits as u32) {
                 Ok(encoded_offset) => Ok(encoded_offset),
                 Err(_) => Err(format!(
-                    "Label {} out of range, requires offset of {} but must be within range -256..255",
-                    label, offset
+                    "Label {} out of range, requires offset of {} but must be within range {}..{}",
+                    label,
+                    offset,
+                    -(1 << (bits - 1)),
+                    (1 << (bits - 1)) - 1
                 )),
             }
         }
         None => return Err(format!("Unrecognized label {}", label)),
     }
 }
+
+/**
+ * The address a label will be loaded at, rather than its address relative to the end of the SLT
+ */
+pub fn get_absolute_label_address(
+    label: &str,
+    label_map: &HashMap<String, u16>,
+    subroutine_lookup_table_entries: &[String],
+) -> Result<u16, String> {
+    match label_map.get(label) {
+        // The SLT is preceded by its size
+        Some(address) => Ok(address + subroutine_lookup_table_entries.len() as u16 + 1),
+        None => Err(format!("Unrecognized label {}", label)),
+    }
+}
+
+/**
+ * Whether a label is within reach of a signed offset with the given number of bits from an address. Unrecognized
+ * labels are reported when the statement referring to them is assembled.
+ */
+pub fn is_label_in_range(address: u16, label: &str, label_map: &HashMap<String, u16>, bits: u16) -> bool {
+    match label_map.get(label) {
+        Some(label_address) => encode_signed_integer(*label_address as i32 - address as i32, bits as u32).is_ok(),
+        None => true,
+    }
+}
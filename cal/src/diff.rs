@@ -0,0 +1,40 @@
+/**
+ * A line by line diff of expected and actual output, with removed (expected) lines prefixed by `-`, added (actual)
+ * lines by `+` and unchanged lines by a space. Lines keep their line endings, and are escaped so that differences in
+ * whitespace and control characters are visible.
+ */
+pub fn diff_lines(expected: &str, actual: &str) -> Vec<String> {
+    let expected: Vec<&str> = expected.split_inclusive('\n').collect();
+    let actual: Vec<&str> = actual.split_inclusive('\n').collect();
+
+    // The length of the longest common subsequence of expected[i..] and actual[j..]
+    let mut lengths = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lengths[i][j] = match expected[i] == actual[j] {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push(format!("  {}", expected[i].escape_debug()));
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && lengths[i + 1][j] >= lengths[i][j + 1]) {
+            lines.push(format!("- {}", expected[i].escape_debug()));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", actual[j].escape_debug()));
+            j += 1;
+        }
+    }
+
+    lines
+}
@@ -0,0 +1,220 @@
+use emulator::{
+    debugger::{parse_number, resolve_location},
+    state::State,
+};
+use shared::debug_info::DebugInfo;
+
+use crate::diff::diff_lines;
+
+// The register index used for the PC
+pub const PC: usize = 8;
+
+/**
+ * A value the machine should hold once a program stops, written `TARGET = VALUE`. The same syntax is used to seed the
+ * machine before it runs.
+ */
+#[derive(Clone, Debug)]
+pub enum Expectation {
+    // R0-R7, or `PC`
+    Register { register: usize, value: u16 },
+    // Words in memory starting at a location
+    Memory { location: String, words: Vec<u16> },
+    ExitStatus(u16),
+}
+
+impl Expectation {
+    /**
+     * Parse `R0 = VALUE` (or `PC`), `[LOCATION] = VALUE...` (consecutive words, or a quoted null terminated string)
+     * or `exit = STATUS`
+     */
+    pub fn parse(expectation: &str) -> Result<Expectation, String> {
+        let (target, value) = expectation.split_once('=').ok_or(format!(
+            "Invalid expectation \"{}\", expected TARGET = VALUE",
+            expectation
+        ))?;
+
+        let (target, value) = (target.trim(), value.trim());
+
+        if target == "exit" {
+            return Ok(Expectation::ExitStatus(parse_number(value)?));
+        }
+
+        if let Some(location) = target.strip_prefix('[').and_then(|target| target.strip_suffix(']')) {
+            let words = match value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) {
+                Some(string) => unescape(string)?
+                    .into_iter()
+                    .map(|byte| byte as u16)
+                    .chain(std::iter::once(0))
+                    .collect(),
+                None => value
+                    .split_whitespace()
+                    .map(parse_number)
+                    .collect::<Result<Vec<u16>, String>>()?,
+            };
+
+            return Ok(Expectation::Memory {
+                location: location.to_string(),
+                words,
+            });
+        }
+
+        Ok(Expectation::Register {
+            register: parse_register(target)?,
+            value: parse_number(value)?,
+        })
+    }
+
+    /**
+     * A description of how the machine differs from the expectation, if it does
+     */
+    pub fn check(&self, state: &State, debug_info: &DebugInfo) -> Option<String> {
+        match self {
+            Expectation::Register { register, value } => {
+                let actual = register_value(state, *register);
+
+                (actual != *value).then(|| {
+                    format!(
+                        "{}: expected {}, got {}",
+                        register_name(*register),
+                        describe(*value),
+                        describe(actual)
+                    )
+                })
+            }
+            Expectation::ExitStatus(status) => {
+                let actual = state.exit_status.unwrap_or(0);
+
+                (actual != *status).then(|| format!("Exit status: expected {}, got {}", status, actual))
+            }
+            Expectation::Memory { location, words } => {
+                let start = match resolve_location(location, Some(debug_info)) {
+                    Ok(start) => start,
+                    Err(e) => return Some(e),
+                };
+
+                let mismatches: Vec<String> = words
+                    .iter()
+                    .enumerate()
+                    .map(|(offset, expected)| (start.wrapping_add(offset as u16), *expected))
+                    .filter(|(address, expected)| state.memory[*address as usize] != *expected)
+                    .map(|(address, expected)| {
+                        format!(
+                            "[{:04X}]: expected {}, got {}",
+                            address,
+                            describe(expected),
+                            describe(state.memory[address as usize])
+                        )
+                    })
+                    .collect();
+
+                (!mismatches.is_empty()).then(|| format!("Memory at {}:\n{}", location, mismatches.join("\n")))
+            }
+        }
+    }
+
+    /**
+     * Make the machine meet the expectation, e.g. to give a subroutine its arguments
+     */
+    pub fn apply(&self, state: &mut State, debug_info: &DebugInfo) -> Result<(), String> {
+        match self {
+            Expectation::Register { register: PC, value } => state.pc = *value,
+            Expectation::Register { register, value } => state.registers[*register] = *value,
+            Expectation::Memory { location, words } => {
+                let start = resolve_location(location, Some(debug_info))?;
+
+                for (offset, word) in words.iter().enumerate() {
+                    let address = start.wrapping_add(offset as u16);
+
+                    state.memory[address as usize] = *word;
+                    state.invalidate_decoded(address);
+                }
+            }
+            Expectation::ExitStatus(_) => return Err("The exit status can't be set".to_string()),
+        }
+
+        Ok(())
+    }
+}
+
+/**
+ * Parse a register name, `R0`-`R7` or `PC`
+ */
+pub fn parse_register(name: &str) -> Result<usize, String> {
+    match name {
+        "PC" => Ok(PC),
+        _ => name
+            .strip_prefix('R')
+            .and_then(|register| register.parse::<usize>().ok())
+            .filter(|register| *register < 8)
+            .ok_or(format!("Unrecognized register {}", name)),
+    }
+}
+
+pub fn register_name(register: usize) -> String {
+    match register {
+        PC => "PC".to_string(),
+        register => format!("R{}", register),
+    }
+}
+
+fn register_value(state: &State, register: usize) -> u16 {
+    match register {
+        PC => state.pc,
+        register => state.registers[register],
+    }
+}
+
+/**
+ * A diff of the program's output against what was expected, if they differ
+ */
+pub fn check_output(expected: &[u8], actual: &[u8]) -> Option<String> {
+    (expected != actual).then(|| {
+        std::iter::once("stdout differs (- expected, + actual):".to_string())
+            .chain(diff_lines(
+                &String::from_utf8_lossy(expected),
+                &String::from_utf8_lossy(actual),
+            ))
+            .collect::<Vec<String>>()
+            .join("\n")
+    })
+}
+
+// A word in decimal and hex, with the character it represents if it's printable ASCII
+pub fn describe(value: u16) -> String {
+    match value {
+        0x20..=0x7E => format!("{} (0x{:04X}, '{}')", value, value, value as u8 as char),
+        _ => format!("{} (0x{:04X})", value, value),
+    }
+}
+
+/**
+ * Convert the escapes `\n`, `\t`, `\r`, `\0`, `\\` and `\"` in text to the bytes they represent
+ */
+pub fn unescape(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let mut characters = text.chars();
+
+    while let Some(character) = characters.next() {
+        let character = match character {
+            '\\' => match characters.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('0') => '\0',
+                Some('\\') => '\\',
+                Some('"') => '"',
+                other => {
+                    return Err(format!(
+                        "Invalid escape \\{}",
+                        other.map(String::from).unwrap_or_default()
+                    ))
+                }
+            },
+            character => character,
+        };
+
+        bytes.extend(character.to_string().bytes());
+    }
+
+    Ok(bytes)
+}
@@ -0,0 +1,167 @@
+use std::{env, fs, path::Path, process};
+
+use assembler::AssembledProgram;
+use emulator::{state::State, Limits, Outcome};
+
+use crate::{
+    expectation::{check_output, describe, register_name, Expectation},
+    machine,
+};
+
+// Labels in the program synthesized to call the subroutine
+const ENTRY_LABEL: &str = "CAL_CALL_ENTRY";
+const RETURN_LABEL: &str = "CAL_CALL_RETURN";
+
+// The instructions the synthesized program executes before the subroutine (a branch to the entry and the CALL), and
+// after it returns (HLT)
+const WRAPPER_INSTRUCTIONS_BEFORE: u64 = 2;
+const WRAPPER_INSTRUCTIONS_AFTER: u64 = 1;
+
+// R7 is the stack pointer, which starts at the same place as the examples' `.STACK_ORIGIN` unless it's set
+const DEFAULT_STACK_POINTER: u16 = 0x3FFF;
+
+/**
+ * A call to a subroutine, and what it should do
+ */
+#[derive(Clone, Debug)]
+pub struct Call {
+    // The subroutine's label, e.g. `.POW`
+    pub label: String,
+    // Registers and memory to set before calling the subroutine
+    pub setup: Vec<Expectation>,
+    pub expectations: Vec<Expectation>,
+    // The registers the subroutine returns values in. Every other register, unless it's expected to hold a value, is
+    // callee-saved and must be the same once the subroutine returns.
+    pub returns: Vec<usize>,
+    pub stdin: Vec<u8>,
+    pub stdout: Option<Vec<u8>>,
+    pub max_instructions: u64,
+}
+
+pub struct CallResult {
+    // The registers the subroutine was called with
+    pub arguments: [u16; 8],
+    // The machine once the subroutine returned (or stopped some other way)
+    pub state: State,
+    pub output: Vec<u8>,
+    // The number of instructions the subroutine executed
+    pub instructions: u64,
+    pub failures: Vec<String>,
+}
+
+/**
+ * Assemble a file, then call a subroutine in it through the SLT and run until it returns
+ */
+pub fn call(path: &Path, call: &Call) -> Result<CallResult, String> {
+    let program = assemble_with_call(path, &call.label)?;
+    let (mut state, output) = machine::load(&program, &call.stdin, &[])?;
+
+    state.registers[7] = DEFAULT_STACK_POINTER;
+
+    for setup in &call.setup {
+        setup.apply(&mut state, &program.debug_info)?;
+    }
+
+    let arguments = state.registers;
+    let return_address = program.debug_info.label_address(RETURN_LABEL).unwrap();
+
+    let limits = Limits {
+        max_instructions: Some(call.max_instructions),
+        timeout: None,
+    };
+
+    let mut failures = Vec::new();
+    let (outcome, executed) = emulator::run_counted(&mut state, &limits);
+    let mut instructions = executed.saturating_sub(WRAPPER_INSTRUCTIONS_BEFORE);
+
+    match outcome {
+        // The PC is left after the HLT following the CALL
+        Outcome::Halted if state.pc == return_address.wrapping_add(1) => instructions -= WRAPPER_INSTRUCTIONS_AFTER,
+        Outcome::Halted => failures.push(format!("Halted at {:04X} before returning", state.pc.wrapping_sub(1))),
+        Outcome::Faulted => failures.push(format!(
+            "Faulted at {:04X}: {}",
+            state.pc,
+            state.fault.as_deref().unwrap_or_default()
+        )),
+        Outcome::InstructionLimitExceeded => {
+            failures.push(format!("Didn't return within {} instructions", call.max_instructions))
+        }
+        Outcome::TimedOut => failures.push("Timed out".to_string()),
+    }
+
+    let output = output.bytes();
+
+    if let Some(expected) = &call.stdout {
+        failures.extend(check_output(expected, &output));
+    }
+
+    failures.extend(
+        call.expectations
+            .iter()
+            .filter_map(|expectation| expectation.check(&state, &program.debug_info)),
+    );
+
+    failures.extend(clobbered_registers(call, &arguments, &state));
+
+    Ok(CallResult {
+        arguments,
+        instructions,
+        state,
+        output,
+        failures,
+    })
+}
+
+fn clobbered_registers(call: &Call, arguments: &[u16; 8], state: &State) -> Vec<String> {
+    let expected: Vec<usize> = call
+        .expectations
+        .iter()
+        .filter_map(|expectation| match expectation {
+            Expectation::Register { register, .. } => Some(*register),
+            _ => None,
+        })
+        .collect();
+
+    (0..8)
+        .filter(|register| !call.returns.contains(register) && !expected.contains(register))
+        .filter(|register| state.registers[*register] != arguments[*register])
+        .map(|register| {
+            format!(
+                "{} is callee-saved but was changed from {} to {}",
+                register_name(register),
+                describe(arguments[register]),
+                describe(state.registers[register])
+            )
+        })
+        .collect()
+}
+
+/**
+ * Assemble a program which includes the file and calls the subroutine, so that the subroutine is called through the
+ * SLT the same way a program would call it
+ */
+fn assemble_with_call(path: &Path, label: &str) -> Result<AssembledProgram, String> {
+    if !label.starts_with('.') {
+        return Err(format!("Invalid label \"{}\", labels start with .", label));
+    }
+
+    let path = std::path::absolute(path).map_err(|e| format!("Invalid path {}: {}", path.display(), e))?;
+
+    let source = format!(
+        "BR nzp .{entry}\nINCLUDE \"{path}\"\n.{entry}\n    CALL {label}\n.{return_label}\n    HLT\n",
+        entry = ENTRY_LABEL,
+        path = path.display(),
+        label = label,
+        return_label = RETURN_LABEL,
+    );
+
+    let wrapper_path = env::temp_dir().join(format!("cal-call-{}.asm", process::id()));
+
+    fs::write(&wrapper_path, source).map_err(|e| format!("Could not write {}: {}", wrapper_path.display(), e))?;
+
+    let program = machine::assemble_file(&wrapper_path);
+
+    let _ = fs::remove_file(&wrapper_path);
+
+    program
+}
@@ -0,0 +1,5 @@
+mod diff;
+pub mod expectation;
+pub mod harness;
+pub mod machine;
+pub mod runner;
@@ -0,0 +1,76 @@
+use std::{
+    io::{self, Write},
+    path::{absolute, Path},
+    sync::{Arc, Mutex},
+};
+
+use assembler::{assemble, AssembledProgram};
+use emulator::{
+    devices::{self, Console, InputQueue},
+    process::load_arguments,
+    state::State,
+};
+
+/**
+ * Console output collected in memory, so it can be compared with what a test expects
+ */
+#[derive(Clone, Default)]
+pub struct CapturedOutput {
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+
+impl CapturedOutput {
+    pub fn bytes(&self) -> Vec<u8> {
+        self.bytes.lock().unwrap().clone()
+    }
+}
+
+impl Write for CapturedOutput {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.bytes.lock().unwrap().extend_from_slice(bytes);
+
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/**
+ * Assemble a file, formatting any error the same way the assembler does
+ */
+pub fn assemble_file(path: &Path) -> Result<AssembledProgram, String> {
+    let path = absolute(path).map_err(|e| format!("Invalid path {}: {}", path.display(), e))?;
+
+    assemble(path.to_string_lossy().into_owned()).map_err(|e| e.to_string())
+}
+
+/**
+ * A machine with an assembled program loaded, the standard devices mapped, and a console which reads `stdin` (then
+ * reaches the end of input) and writes to the returned output
+ */
+pub fn load(program: &AssembledProgram, stdin: &[u8], arguments: &[String]) -> Result<(State, CapturedOutput), String> {
+    let mut state = State::new();
+
+    let bytes: Vec<u8> = program
+        .machine_code
+        .iter()
+        .flat_map(|word| word.to_be_bytes())
+        .collect();
+
+    state.load_binary(&bytes);
+    load_arguments(&mut state, arguments, program.machine_code.len())?;
+
+    let input = InputQueue::default();
+
+    input.push(stdin);
+    input.close();
+
+    let output = CapturedOutput::default();
+    let console = Console::from_queue(input).with_output(Box::new(output.clone()));
+
+    devices::map_standard_devices(&mut state.bus, console);
+
+    Ok((state, output))
+}
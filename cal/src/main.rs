@@ -0,0 +1,154 @@
+use std::{path::Path, process};
+
+use cal::{
+    expectation::{describe, parse_register, register_name, unescape, Expectation},
+    harness::{self, Call},
+    runner::run_tests,
+};
+
+const USAGE: &str = "Usage:
+  cal test [PATH...]
+  cal call FILE .LABEL [--set TARGET=VALUE]... [--expect TARGET=VALUE]... [--returns REGISTERS] [--stdin TEXT]
+                       [--stdout TEXT] [--max-instructions N]";
+
+// Subroutines return their result in R0 unless told otherwise
+const DEFAULT_RETURNS: &[usize] = &[0];
+
+const DEFAULT_MAX_INSTRUCTIONS: u64 = 10_000_000;
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let exit_code = match args.first().map(String::as_str) {
+        Some("test") => test(&args[1..]),
+        Some("call") => call(&args[1..]),
+        _ => {
+            eprintln!("{}", USAGE);
+            2
+        }
+    };
+
+    process::exit(exit_code);
+}
+
+/**
+ * Run the tests in some files and directories (the current directory by default), exiting with 0 if they all pass, 1
+ * if any fail and 2 on error
+ */
+fn test(paths: &[String]) -> i32 {
+    let paths = match paths.is_empty() {
+        true => vec![".".to_string()],
+        false => paths.to_vec(),
+    };
+
+    match run_tests(&paths) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            eprintln!("{}", e);
+            2
+        }
+    }
+}
+
+/**
+ * Call a subroutine and report what it returned, exiting with 0 if it did what was expected, 1 if not and 2 on error
+ */
+fn call(args: &[String]) -> i32 {
+    let (path, call) = match parse_call(args) {
+        Ok(call) => call,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+
+    let result = match harness::call(Path::new(&path), &call) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("{}", e.trim_end());
+            return 2;
+        }
+    };
+
+    println!("{} ran for {} instructions", call.label, result.instructions);
+
+    for (register, value) in result.state.registers.iter().enumerate() {
+        match *value == result.arguments[register] {
+            true => println!("{}: {}", register_name(register), describe(*value)),
+            false => println!(
+                "{}: {} (was {})",
+                register_name(register),
+                describe(*value),
+                describe(result.arguments[register])
+            ),
+        }
+    }
+
+    if !result.output.is_empty() {
+        println!("Output: \"{}\"", String::from_utf8_lossy(&result.output).escape_debug());
+    }
+
+    match result.failures.is_empty() {
+        true => {
+            println!("PASS");
+            0
+        }
+        false => {
+            for failure in &result.failures {
+                println!("FAIL {}", failure.replace('\n', "\n    "));
+            }
+
+            1
+        }
+    }
+}
+
+fn parse_call(args: &[String]) -> Result<(String, Call), String> {
+    let mut args = args.iter().cloned();
+
+    let path = args.next().ok_or("No file provided")?;
+    let label = args.next().ok_or("No label provided")?;
+
+    let mut call = Call {
+        label,
+        setup: Vec::new(),
+        expectations: Vec::new(),
+        returns: DEFAULT_RETURNS.to_vec(),
+        stdin: Vec::new(),
+        stdout: None,
+        max_instructions: DEFAULT_MAX_INSTRUCTIONS,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--set" => call.setup.push(Expectation::parse(&next_value(&mut args, &arg)?)?),
+            "--expect" => call
+                .expectations
+                .push(Expectation::parse(&next_value(&mut args, &arg)?)?),
+            "--returns" => {
+                call.returns = next_value(&mut args, &arg)?
+                    .split(',')
+                    .filter(|register| !register.is_empty())
+                    .map(|register| parse_register(register.trim()))
+                    .collect::<Result<Vec<usize>, String>>()?
+            }
+            "--stdin" => call.stdin = unescape(&next_value(&mut args, &arg)?)?,
+            "--stdout" => call.stdout = Some(unescape(&next_value(&mut args, &arg)?)?),
+            "--max-instructions" => {
+                let value = next_value(&mut args, &arg)?;
+
+                call.max_instructions = value
+                    .parse()
+                    .map_err(|_| format!("Invalid instruction limit \"{}\"", value))?;
+            }
+            _ => return Err(format!("Unrecognized option {}", arg)),
+        }
+    }
+
+    Ok((path, call))
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+    args.next().ok_or(format!("No value provided for {}", option))
+}
@@ -0,0 +1,305 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use assembler::AssembledProgram;
+use emulator::{Limits, Outcome};
+
+use crate::{
+    expectation::{check_output, unescape, Expectation},
+    machine,
+};
+
+// Programs are stopped after this many instructions unless a test says otherwise, so a test which loops forever fails
+const DEFAULT_MAX_INSTRUCTIONS: u64 = 10_000_000;
+
+// Test files annotate a program inline, sidecar files sit next to the program they test
+const TEST_FILE_EXTENSION: &str = ".test.asm";
+const SIDECAR_EXTENSION: &str = "test";
+
+/**
+ * A file of test cases and the program they run
+ */
+#[derive(Clone, Debug)]
+pub struct TestFile {
+    pub path: PathBuf,
+    pub program: PathBuf,
+}
+
+impl TestFile {
+    // Whether the annotations are comments in the program itself rather than in a sidecar file
+    fn is_inline(&self) -> bool {
+        self.path == self.program
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TestCase {
+    pub name: String,
+    pub stdin: Vec<u8>,
+    // The output is only checked if some is expected
+    pub stdout: Option<Vec<u8>>,
+    pub arguments: Vec<String>,
+    pub expectations: Vec<Expectation>,
+    pub max_instructions: u64,
+}
+
+impl Default for TestCase {
+    fn default() -> TestCase {
+        TestCase {
+            name: String::new(),
+            stdin: Vec::new(),
+            stdout: None,
+            arguments: Vec::new(),
+            expectations: Vec::new(),
+            max_instructions: DEFAULT_MAX_INSTRUCTIONS,
+        }
+    }
+}
+
+/**
+ * Find the tests in some files and directories (searched recursively): `*.test.asm` files annotated with comments,
+ * and `*.test` sidecar files next to the `*.asm` program they test
+ */
+pub fn discover(paths: &[String]) -> Result<Vec<TestFile>, String> {
+    let mut tests = Vec::new();
+
+    for path in paths {
+        let path = Path::new(path);
+
+        match path.is_dir() {
+            true => discover_directory(path, &mut tests)?,
+            false => match test_file(path) {
+                Some(test) => tests.push(test),
+                None => return Err(format!("{} is not a test file", path.display())),
+            },
+        }
+    }
+
+    Ok(tests)
+}
+
+fn discover_directory(directory: &Path, tests: &mut Vec<TestFile>) -> Result<(), String> {
+    let entries = fs::read_dir(directory).map_err(|e| format!("Could not read {}: {}", directory.display(), e))?;
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+
+    paths.sort();
+
+    for path in paths {
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.') || name == "target");
+
+        if path.is_dir() && !hidden {
+            discover_directory(&path, tests)?;
+        } else if let Some(test) = test_file(&path) {
+            tests.push(test);
+        }
+    }
+
+    Ok(())
+}
+
+fn test_file(path: &Path) -> Option<TestFile> {
+    if path.to_string_lossy().ends_with(TEST_FILE_EXTENSION) {
+        return Some(TestFile {
+            path: path.to_path_buf(),
+            program: path.to_path_buf(),
+        });
+    }
+
+    let program = path.with_extension("asm");
+
+    match path.extension().is_some_and(|extension| extension == SIDECAR_EXTENSION) && program.is_file() {
+        true => Some(TestFile {
+            path: path.to_path_buf(),
+            program,
+        }),
+        false => None,
+    }
+}
+
+/**
+ * Parse the test cases in a test file's annotations, which are lines of the form `@DIRECTIVE ARGUMENTS` (written as
+ * `// @DIRECTIVE ARGUMENTS` comments in `.test.asm` files):
+ *
+ * - `@case NAME` starts a new case. Directives before the first case apply to every case.
+ * - `@stdin TEXT` and `@stdout TEXT` append to the program's input and expected output. `\n`, `\t`, `\r`, `\0`, `\\`
+ *   and `\"` are escapes.
+ * - `@args ARGUMENTS` sets the program's command line arguments, separated by whitespace.
+ * - `@expect R0 = VALUE` (or `PC`), `@expect [LOCATION] = VALUE...` (consecutive words, or a quoted null terminated
+ *   string) and `@expect exit = STATUS` check the machine once the program halts.
+ * - `@max-instructions N` fails the case if the program doesn't halt within N instructions.
+ */
+pub fn parse_cases(source: &str, inline: bool) -> Result<Vec<TestCase>, String> {
+    let mut defaults = TestCase::default();
+    let mut cases: Vec<TestCase> = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+
+        let annotation = match inline {
+            true => line
+                .strip_prefix("//")
+                .map(str::trim_start)
+                .filter(|line| line.starts_with('@')),
+            false => Some(line).filter(|line| !line.is_empty() && !line.starts_with("//")),
+        };
+
+        let Some(annotation) = annotation else {
+            continue;
+        };
+
+        let (directive, arguments) = annotation.split_once(char::is_whitespace).unwrap_or((annotation, ""));
+        let arguments = arguments.trim();
+
+        if directive == "@case" {
+            cases.push(TestCase {
+                name: arguments.to_string(),
+                ..defaults.clone()
+            });
+
+            continue;
+        }
+
+        let case = cases.last_mut().unwrap_or(&mut defaults);
+
+        parse_directive(case, directive, arguments).map_err(|e| format!("Line {}: {}", index + 1, e))?;
+    }
+
+    match cases.is_empty() {
+        true => Ok(vec![defaults]),
+        false => Ok(cases),
+    }
+}
+
+fn parse_directive(case: &mut TestCase, directive: &str, arguments: &str) -> Result<(), String> {
+    match directive {
+        "@stdin" => case.stdin.extend(unescape(arguments)?),
+        "@stdout" => case.stdout.get_or_insert_with(Vec::new).extend(unescape(arguments)?),
+        "@args" => case.arguments = arguments.split_whitespace().map(str::to_string).collect(),
+        "@expect" => case.expectations.push(Expectation::parse(arguments)?),
+        "@max-instructions" => {
+            case.max_instructions = arguments
+                .parse()
+                .map_err(|_| format!("Invalid instruction limit \"{}\"", arguments))?
+        }
+        _ => return Err(format!("Unrecognized directive {}", directive)),
+    }
+
+    Ok(())
+}
+
+/**
+ * Run a test case, returning a description of each way it failed
+ */
+pub fn run_case(program: &AssembledProgram, case: &TestCase) -> Vec<String> {
+    let (mut state, output) = match machine::load(program, &case.stdin, &case.arguments) {
+        Ok(machine) => machine,
+        Err(e) => return vec![e],
+    };
+
+    let limits = Limits {
+        max_instructions: Some(case.max_instructions),
+        timeout: None,
+    };
+
+    let mut failures = Vec::new();
+
+    match emulator::run(&mut state, &limits) {
+        Outcome::Halted => {}
+        Outcome::Faulted => failures.push(format!(
+            "Faulted at {:04X}: {}",
+            state.pc,
+            state.fault.as_deref().unwrap_or_default()
+        )),
+        Outcome::InstructionLimitExceeded => {
+            failures.push(format!("Didn't halt within {} instructions", case.max_instructions))
+        }
+        Outcome::TimedOut => failures.push("Timed out".to_string()),
+    }
+
+    if let Some(expected) = &case.stdout {
+        failures.extend(check_output(expected, &output.bytes()));
+    }
+
+    failures.extend(
+        case.expectations
+            .iter()
+            .filter_map(|expectation| expectation.check(&state, &program.debug_info)),
+    );
+
+    failures
+}
+
+/**
+ * Run every test case in some files and directories, printing whether each passed or failed, returning whether they
+ * all passed
+ */
+pub fn run_tests(paths: &[String]) -> Result<bool, String> {
+    let tests = discover(paths)?;
+
+    if tests.is_empty() {
+        return Err("No tests found".to_string());
+    }
+
+    let mut passed = 0;
+    let mut failed = 0;
+
+    for test in &tests {
+        let source =
+            fs::read_to_string(&test.path).map_err(|e| format!("Could not read {}: {}", test.path.display(), e))?;
+
+        let cases = parse_cases(&source, test.is_inline()).map_err(|e| format!("{}: {}", test.path.display(), e))?;
+
+        let program = match machine::assemble_file(&test.program) {
+            Ok(program) => program,
+            Err(e) => {
+                println!("FAIL {}", test.path.display());
+                println!("  Could not assemble {}:", test.program.display());
+
+                for line in e.lines() {
+                    println!("  {}", line);
+                }
+
+                failed += 1;
+                continue;
+            }
+        };
+
+        for case in &cases {
+            let name = match case.name.is_empty() {
+                true => test.path.display().to_string(),
+                false => format!("{}: {}", test.path.display(), case.name),
+            };
+
+            let failures = run_case(&program, case);
+
+            match failures.is_empty() {
+                true => {
+                    println!("PASS {}", name);
+                    passed += 1;
+                }
+                false => {
+                    println!("FAIL {}", name);
+
+                    for failure in failures {
+                        // Details of a failure are indented under its first line
+                        println!("  {}", failure.replace('\n', "\n    "));
+                    }
+
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    println!("\n{} passed, {} failed", passed, failed);
+
+    Ok(failed == 0)
+}
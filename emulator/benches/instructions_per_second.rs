@@ -0,0 +1,57 @@
+use std::time::Instant;
+
+use emulator::{
+    devices::{console, Console},
+    state::State,
+};
+
+// Counts R0 up from 0 until it overflows into negative numbers, 100 times
+const PROGRAM: [u16; 10] = [
+    0x0000, // Empty SLT
+    0x7200, // LDI R1 #0
+    0x7000, // LDI R0 #0
+    0x0001, // ADD R0 R0 #1
+    0x97FE, // BR zp #-2
+    0x0241, // ADD R1 R1 #1
+    0x7464, // LDI R2 #100
+    0x1468, // SUB R2 R1 R2
+    0x99F9, // BR n #-7
+    0xC000, // HLT
+];
+
+const RUNS: usize = 5;
+
+/**
+ * Measure the number of instructions executed per second by a tight loop, with the console mapped as it is when
+ * running a program normally
+ */
+fn main() {
+    let bytes: Vec<u8> = PROGRAM.iter().flat_map(|word| word.to_be_bytes()).collect();
+
+    for run in 1..=RUNS {
+        let mut state = State::new();
+
+        state.load_binary(&bytes);
+        state
+            .bus
+            .map(0xFFFC..=0xFFFC + (console::SIZE - 1), Box::new(Console::host()), None);
+
+        let start = Instant::now();
+        let mut instructions: u64 = 0;
+
+        while !state.halt {
+            emulator::step(&mut state);
+            instructions += 1;
+        }
+
+        let elapsed = start.elapsed();
+
+        println!(
+            "Run {}: {} instructions in {:.3}s, {:.0} instructions per second",
+            run,
+            instructions,
+            elapsed.as_secs_f64(),
+            instructions as f64 / elapsed.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+// The number of cycles in a millisecond of virtual time, i.e. the emulated CPU runs at 1MHz
+pub const CYCLES_PER_MILLISECOND: u64 = 1000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimeMode {
+    // Time is derived from the cycles executed and SLP advances it instantly, so every run behaves identically
+    Virtual,
+    // SLP blocks the host thread and milliseconds are measured by the host's clock
+    Realtime,
+}
+
+/**
+ * Counts the cycles executed by the CPU and keeps track of the current time, which devices read when they are ticked.
+ *
+ * Every instruction costs a configurable number of cycles depending on its opcode, by default one.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct Clock {
+    pub mode: TimeMode,
+    cycles: u64,
+    // The cycles executed plus the time spent sleeping, in cycles, which virtual time is derived from
+    virtual_cycles: u64,
+    start: Instant,
+    cycle_costs: [u16; 16],
+}
+
+impl Clock {
+    pub fn new(mode: TimeMode) -> Clock {
+        Clock {
+            mode,
+            cycles: 0,
+            virtual_cycles: 0,
+            start: Instant::now(),
+            cycle_costs: [1; 16],
+        }
+    }
+
+    pub fn set_cycle_cost(&mut self, opcode: u8, cycles: u16) {
+        self.cycle_costs[opcode as usize] = cycles;
+    }
+
+    pub fn cycle_cost(&self, opcode: u8) -> u16 {
+        self.cycle_costs[opcode as usize]
+    }
+
+    /**
+     * Advance the clock by the cost of executing an instruction with an opcode
+     */
+    pub fn execute(&mut self, opcode: u8) {
+        let cycles = self.cycle_costs[opcode as usize] as u64;
+
+        self.cycles += cycles;
+        self.virtual_cycles += cycles;
+    }
+
+    pub fn sleep(&mut self, milliseconds: u16) {
+        match self.mode {
+            TimeMode::Virtual => self.virtual_cycles += milliseconds as u64 * CYCLES_PER_MILLISECOND,
+            TimeMode::Realtime => thread::sleep(Duration::from_millis(milliseconds.into())),
+        }
+    }
+
+    /**
+     * The number of cycles executed, not including time spent sleeping
+     */
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /**
+     * The cycles executed plus the time spent sleeping, in cycles
+     */
+    pub fn virtual_cycles(&self) -> u64 {
+        self.virtual_cycles
+    }
+
+    /**
+     * Set the clock to a time returned by `cycles` and `virtual_cycles`, e.g. when restoring a snapshot
+     */
+    pub fn set_time(&mut self, cycles: u64, virtual_cycles: u64) {
+        self.cycles = cycles;
+        self.virtual_cycles = virtual_cycles;
+    }
+
+    /**
+     * The number of milliseconds since the program started
+     */
+    pub fn milliseconds(&self) -> u64 {
+        match self.mode {
+            TimeMode::Virtual => self.virtual_cycles / CYCLES_PER_MILLISECOND,
+            TimeMode::Realtime => self.start.elapsed().as_millis() as u64,
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock::new(TimeMode::Virtual)
+    }
+}
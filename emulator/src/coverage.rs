@@ -0,0 +1,295 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    fs,
+};
+
+use shared::debug_info::{DebugInfo, FrameKind, SourceFrame};
+
+#[derive(Clone, Copy, Default)]
+pub struct BranchOutcomes {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+/**
+ * Records which addresses were executed and the outcomes of every executed branch
+ */
+pub struct Coverage {
+    execution_counts: Vec<u64>,
+    branch_outcomes: HashMap<u16, BranchOutcomes>,
+}
+
+// A conditional branch in the source along with its outcomes (or None if it never executed)
+struct SourceBranch {
+    address: u16,
+    outcomes: Option<BranchOutcomes>,
+}
+
+// Line coverage keyed by (file index, line)
+type LineMap<T> = BTreeMap<(usize, usize), T>;
+
+impl Coverage {
+    pub fn new() -> Coverage {
+        Coverage {
+            execution_counts: vec![0; 65536],
+            branch_outcomes: HashMap::new(),
+        }
+    }
+
+    pub fn record_execution(&mut self, address: u16) {
+        self.execution_counts[address as usize] += 1;
+    }
+
+    pub fn record_branch(&mut self, address: u16, taken: bool) {
+        let outcomes = self.branch_outcomes.entry(address).or_default();
+
+        match taken {
+            true => outcomes.taken += 1,
+            false => outcomes.not_taken += 1,
+        }
+    }
+
+    pub fn execution_count(&self, address: u16) -> u64 {
+        self.execution_counts[address as usize]
+    }
+
+    /**
+     * Generate an lcov tracefile from the recorded coverage
+     */
+    pub fn to_lcov(&self, debug_info: &DebugInfo, memory: &[u16]) -> String {
+        let line_hits = self.line_hits(debug_info);
+        let branches = self.branches(debug_info, memory);
+
+        let mut out = String::new();
+
+        writeln!(out, "TN:").unwrap();
+
+        for (file_index, file) in debug_info.files.iter().enumerate() {
+            let file_lines: Vec<(usize, u64)> = lines_in_file(&line_hits, file_index)
+                .map(|(line, hits)| (line, *hits))
+                .collect();
+
+            if file_lines.is_empty() {
+                continue;
+            }
+
+            writeln!(out, "SF:{}", file).unwrap();
+
+            let mut branches_found = 0;
+            let mut branches_hit = 0;
+
+            for (line, line_branches) in lines_in_file(&branches, file_index) {
+                for branch in line_branches {
+                    let counts = match branch.outcomes {
+                        Some(outcomes) => [outcomes.taken.to_string(), outcomes.not_taken.to_string()],
+                        None => ["-".to_string(), "-".to_string()],
+                    };
+
+                    for (index, count) in counts.iter().enumerate() {
+                        writeln!(out, "BRDA:{},{},{},{}", line, branch.address, index, count).unwrap();
+                    }
+
+                    branches_found += 2;
+                    branches_hit += counts.iter().filter(|count| *count != "-" && *count != "0").count();
+                }
+            }
+
+            writeln!(out, "BRF:{}", branches_found).unwrap();
+            writeln!(out, "BRH:{}", branches_hit).unwrap();
+
+            for (line, hits) in &file_lines {
+                writeln!(out, "DA:{},{}", line, hits).unwrap();
+            }
+
+            writeln!(out, "LF:{}", file_lines.len()).unwrap();
+            writeln!(out, "LH:{}", file_lines.iter().filter(|(_, hits)| *hits > 0).count()).unwrap();
+            writeln!(out, "end_of_record").unwrap();
+        }
+
+        out
+    }
+
+    /**
+     * Generate a human readable report containing the source of each file annotated with line hit counts and
+     * branch outcomes
+     */
+    pub fn to_annotated_report(&self, debug_info: &DebugInfo, memory: &[u16]) -> Result<String, String> {
+        let line_hits = self.line_hits(debug_info);
+        let branches = self.branches(debug_info, memory);
+
+        let mut out = String::new();
+
+        for (file_index, file) in debug_info.files.iter().enumerate() {
+            let lines_found = lines_in_file(&line_hits, file_index).count();
+
+            if lines_found == 0 {
+                continue;
+            }
+
+            let source = fs::read_to_string(file).map_err(|e| format!("Unable to read file {}: {}", file, e))?;
+
+            let lines_hit = lines_in_file(&line_hits, file_index)
+                .filter(|(_, hits)| **hits > 0)
+                .count();
+
+            let file_branches: Vec<&SourceBranch> = lines_in_file(&branches, file_index)
+                .flat_map(|(_, line_branches)| line_branches)
+                .collect();
+
+            let branch_outcomes_hit: usize = file_branches
+                .iter()
+                .filter_map(|branch| branch.outcomes)
+                .map(|outcomes| (outcomes.taken > 0) as usize + (outcomes.not_taken > 0) as usize)
+                .sum();
+
+            writeln!(out, "File: {}", file).unwrap();
+            writeln!(
+                out,
+                "Lines: {}/{} ({})",
+                lines_hit,
+                lines_found,
+                format_percentage(lines_hit, lines_found)
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "Branches: {}/{} ({})\n",
+                branch_outcomes_hit,
+                file_branches.len() * 2,
+                format_percentage(branch_outcomes_hit, file_branches.len() * 2)
+            )
+            .unwrap();
+
+            for (line_index, line) in source.lines().enumerate() {
+                let line_number = line_index + 1;
+
+                let hits = match line_hits.get(&(file_index, line_number)) {
+                    Some(0) => "#####".to_string(),
+                    Some(hits) => hits.to_string(),
+                    None => "-".to_string(),
+                };
+
+                write!(out, "{:>9} | {}", hits, line).unwrap();
+
+                for branch in branches.get(&(file_index, line_number)).into_iter().flatten() {
+                    match branch.outcomes {
+                        Some(outcomes) => write!(
+                            out,
+                            "  [branch {:04X}: taken {}, not taken {}]",
+                            branch.address, outcomes.taken, outcomes.not_taken
+                        )
+                        .unwrap(),
+                        None => write!(out, "  [branch {:04X}: never executed]", branch.address).unwrap(),
+                    }
+                }
+
+                writeln!(out).unwrap();
+            }
+
+            writeln!(out).unwrap();
+        }
+
+        Ok(out)
+    }
+
+    /**
+     * Compute the number of times each source line was executed.
+     *
+     * A line is counted once for each time the statement written on it executed. Macro invocations are counted once
+     * per execution of their expansion (i.e. the most executed statement in the expansion) and include directives
+     * are never counted.
+     */
+    fn line_hits(&self, debug_info: &DebugInfo) -> LineMap<u64> {
+        // Keyed by the backtrace up to and including the frame so that each expansion of a macro is counted separately
+        let mut expansion_hits: HashMap<&[SourceFrame], u64> = HashMap::new();
+
+        for statement in debug_info
+            .statements
+            .iter()
+            .filter(|statement| statement.is_instruction)
+        {
+            let hits = self.execution_count(statement.address);
+
+            for (index, frame) in statement.backtrace.iter().enumerate() {
+                if frame.kind == FrameKind::Include {
+                    continue;
+                }
+
+                let expansion = expansion_hits.entry(&statement.backtrace[..=index]).or_default();
+
+                *expansion = (*expansion).max(hits);
+            }
+        }
+
+        let mut line_hits = LineMap::new();
+
+        for (expansion, hits) in expansion_hits {
+            let frame = expansion.last().unwrap();
+
+            *line_hits.entry((frame.file, frame.line)).or_default() += hits;
+        }
+
+        line_hits
+    }
+
+    fn branches(&self, debug_info: &DebugInfo, memory: &[u16]) -> LineMap<Vec<SourceBranch>> {
+        let mut branches: LineMap<Vec<SourceBranch>> = LineMap::new();
+
+        for statement in debug_info
+            .statements
+            .iter()
+            .filter(|statement| statement.is_instruction)
+        {
+            let machine_code = memory[statement.address as usize];
+
+            // Skip anything which isn't a branch, as well as unconditional branches which have only one outcome
+            if machine_code >> 12 != 0x9 || (machine_code >> 9) & 0b111 == 0b111 {
+                continue;
+            }
+
+            let location = statement.location();
+            let mut outcomes = self.branch_outcomes.get(&statement.address).copied();
+
+            // The assembler relaxes a branch to a label out of range into the opposite branch over a JMPL, which is
+            // taken when the written branch isn't
+            let relaxed = statement.width == 3 && memory[statement.address.wrapping_add(1) as usize] >> 8 == 0xE7;
+
+            if let (true, Some(BranchOutcomes { taken, not_taken })) = (relaxed, outcomes) {
+                outcomes = Some(BranchOutcomes {
+                    taken: not_taken,
+                    not_taken: taken,
+                });
+            }
+
+            branches
+                .entry((location.file, location.line))
+                .or_default()
+                .push(SourceBranch {
+                    address: statement.address,
+                    outcomes,
+                });
+        }
+
+        branches
+    }
+}
+
+impl Default for Coverage {
+    fn default() -> Coverage {
+        Coverage::new()
+    }
+}
+
+fn lines_in_file<T>(line_map: &LineMap<T>, file_index: usize) -> impl Iterator<Item = (usize, &T)> {
+    line_map
+        .range((file_index, 0)..(file_index + 1, 0))
+        .map(|((_, line), value)| (*line, value))
+}
+
+fn format_percentage(numerator: usize, denominator: usize) -> String {
+    match denominator {
+        0 => "-".to_string(),
+        _ => format!("{:.1}%", numerator as f64 * 100.0 / denominator as f64),
+    }
+}
@@ -0,0 +1,280 @@
+use std::fmt::{Display, Formatter, Result as FormatResult};
+
+use shared::BranchConditions;
+
+use crate::state::State;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    Register(usize),
+    Pc,
+    Flag(BranchConditions),
+    Memory(Box<Operand>),
+    Literal(u16),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expression {
+    Operand(Operand),
+    Compare(Operand, Comparison, Operand),
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+/**
+ * A condition on the machine state, e.g. `R0 == 5 && [0x1000] != 0`
+ *
+ * Operands are registers (`R0`-`R7`, `PC`), flags (`N`, `Z`, `P`, `C` and `V` which evaluate to 1 when set), memory
+ * (`[R7]`, `[0x3FFF]`) and numeric literals (`5`, `#5`, `0xFF`, `#-1`). Comparisons are unsigned and can be combined
+ * with `&&`, `||`, `!` and parentheses. An operand on its own is true when it is non-zero.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    source: String,
+    expression: Expression,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Condition, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { tokens, position: 0 };
+
+        let expression = parser.parse_or()?;
+
+        if let Some(token) = parser.peek() {
+            return Err(format!("Unexpected \"{}\" in condition", token));
+        }
+
+        Ok(Condition {
+            source: source.trim().to_owned(),
+            expression,
+        })
+    }
+
+    pub fn evaluate(&self, state: &State) -> bool {
+        evaluate_expression(&self.expression, state)
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        write!(f, "{}", self.source)
+    }
+}
+
+fn evaluate_expression(expression: &Expression, state: &State) -> bool {
+    match expression {
+        Expression::Operand(operand) => evaluate_operand(operand, state) != 0,
+        Expression::Compare(left, comparison, right) => {
+            let left = evaluate_operand(left, state);
+            let right = evaluate_operand(right, state);
+
+            match comparison {
+                Comparison::Equal => left == right,
+                Comparison::NotEqual => left != right,
+                Comparison::LessThan => left < right,
+                Comparison::LessThanOrEqual => left <= right,
+                Comparison::GreaterThan => left > right,
+                Comparison::GreaterThanOrEqual => left >= right,
+            }
+        }
+        Expression::Not(inner) => !evaluate_expression(inner, state),
+        Expression::And(left, right) => evaluate_expression(left, state) && evaluate_expression(right, state),
+        Expression::Or(left, right) => evaluate_expression(left, state) || evaluate_expression(right, state),
+    }
+}
+
+fn evaluate_operand(operand: &Operand, state: &State) -> u16 {
+    match operand {
+        Operand::Register(register) => state.registers[*register],
+        Operand::Pc => state.pc,
+        Operand::Flag(flag) => state.flags.contains(*flag) as u16,
+        // Conditions read memory directly so evaluating them doesn't trigger watchpoints
+        Operand::Memory(address) => state.memory[evaluate_operand(address, state) as usize],
+        Operand::Literal(value) => *value,
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<String>, String> {
+    let characters: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < characters.len() {
+        let character = characters[index];
+
+        if character.is_whitespace() {
+            index += 1;
+            continue;
+        }
+
+        let two_characters: String = characters[index..(index + 2).min(characters.len())].iter().collect();
+
+        if ["==", "!=", "<=", ">=", "&&", "||"].contains(&two_characters.as_str()) {
+            tokens.push(two_characters);
+            index += 2;
+        } else if "<>![]()".contains(character) {
+            tokens.push(character.to_string());
+            index += 1;
+        } else if character.is_ascii_alphanumeric() || character == '#' || character == '-' {
+            let start = index;
+
+            // Allow numeric literals of the form #-1
+            if characters[index] == '#' && characters.get(index + 1) == Some(&'-') {
+                index += 1;
+            }
+
+            index += 1;
+
+            while index < characters.len() && characters[index].is_ascii_alphanumeric() {
+                index += 1;
+            }
+
+            tokens.push(characters[start..index].iter().collect());
+        } else {
+            return Err(format!("Unexpected character '{}' in condition", character));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.position).map(|token| token.as_str())
+    }
+
+    fn next(&mut self) -> Result<String, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or("Unexpected end of condition")?;
+
+        self.position += 1;
+
+        Ok(token)
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!(
+                "Expected \"{}\" but found \"{}\" in condition",
+                expected, token
+            )),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_and()?;
+
+        while self.peek() == Some("||") {
+            self.position += 1;
+            expression = Expression::Or(Box::new(expression), Box::new(self.parse_and()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_unary()?;
+
+        while self.peek() == Some("&&") {
+            self.position += 1;
+            expression = Expression::And(Box::new(expression), Box::new(self.parse_unary()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, String> {
+        match self.peek() {
+            Some("!") => {
+                self.position += 1;
+                Ok(Expression::Not(Box::new(self.parse_unary()?)))
+            }
+            Some("(") => {
+                self.position += 1;
+                let expression = self.parse_or()?;
+                self.expect(")")?;
+                Ok(expression)
+            }
+            _ => self.parse_comparison(),
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expression, String> {
+        let left = self.parse_operand()?;
+
+        let comparison = match self.peek() {
+            Some("==") => Comparison::Equal,
+            Some("!=") => Comparison::NotEqual,
+            Some("<") => Comparison::LessThan,
+            Some("<=") => Comparison::LessThanOrEqual,
+            Some(">") => Comparison::GreaterThan,
+            Some(">=") => Comparison::GreaterThanOrEqual,
+            _ => return Ok(Expression::Operand(left)),
+        };
+
+        self.position += 1;
+
+        Ok(Expression::Compare(left, comparison, self.parse_operand()?))
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand, String> {
+        let token = self.next()?;
+
+        match token.as_str() {
+            "[" => {
+                let address = self.parse_operand()?;
+                self.expect("]")?;
+                Ok(Operand::Memory(Box::new(address)))
+            }
+            "PC" => Ok(Operand::Pc),
+            "N" => Ok(Operand::Flag(BranchConditions::NEGATIVE)),
+            "Z" => Ok(Operand::Flag(BranchConditions::ZERO)),
+            "P" => Ok(Operand::Flag(BranchConditions::POSITIVE)),
+            "C" => Ok(Operand::Flag(BranchConditions::CARRY)),
+            "V" => Ok(Operand::Flag(BranchConditions::OVERFLOW)),
+            _ if token.len() == 2 && token.starts_with('R') && ('0'..='7').contains(&token.chars().nth(1).unwrap()) => {
+                Ok(Operand::Register(token[1..].parse().unwrap()))
+            }
+            _ => parse_number(&token).map(Operand::Literal),
+        }
+    }
+}
+
+/**
+ * Parse a decimal (`12`, `#-3`) or hexadecimal (`0x3FFF`) number, wrapping negative values to 16 bits
+ */
+pub fn parse_number(token: &str) -> Result<u16, String> {
+    let digits = token.strip_prefix('#').unwrap_or(token);
+
+    let value = match digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        Some(hex_digits) => i32::from_str_radix(hex_digits, 16),
+        None => digits.parse::<i32>(),
+    }
+    .map_err(|_| format!("Invalid number \"{}\"", token))?;
+
+    match value {
+        -32768..=65535 => Ok(value as u16),
+        _ => Err(format!("Number \"{}\" does not fit in 16 bits", token)),
+    }
+}
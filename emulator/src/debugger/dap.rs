@@ -0,0 +1,586 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+use serde_json::{json, Value};
+use shared::{
+    debug_info::{DebugInfo, FrameKind, SourceFrame},
+    BranchConditions,
+};
+
+use super::{parse_number, Condition, Debugger, StopReason};
+
+// DAP clients can debug several threads, the emulator only has one
+const THREAD_ID: u64 = 1;
+
+// Variable references for the scopes shown for every stack frame
+const REGISTERS_REFERENCE: u64 = 1;
+const FLAGS_REFERENCE: u64 = 2;
+
+const FLAGS: [(&str, BranchConditions); 5] = [
+    ("N", BranchConditions::NEGATIVE),
+    ("Z", BranchConditions::ZERO),
+    ("P", BranchConditions::POSITIVE),
+    ("C", BranchConditions::CARRY),
+    ("V", BranchConditions::OVERFLOW),
+];
+
+/**
+ * A Debug Adapter Protocol server, which drives a `Debugger` on behalf of an editor such as VS Code.
+ *
+ * Source lines are mapped to addresses using debug info. The stack frames shown for an address are the lines it was
+ * assembled from (the line inside a macro, then the line invoking the macro), followed by the same for each CALL on
+ * the call stack.
+ */
+pub struct DapServer<'a> {
+    debugger: &'a mut Debugger,
+    debug_info: Option<&'a DebugInfo>,
+    input: Receiver<Value>,
+    output: Box<dyn Write>,
+    seq: u64,
+    // The debugger breakpoints set in each source file, by the path the client gave
+    breakpoints: HashMap<String, Vec<usize>>,
+    stop_on_entry: bool,
+}
+
+impl<'a> DapServer<'a> {
+    /**
+     * Messages are read from `reader` on a background thread, so that the client can pause a running program
+     */
+    pub fn new(
+        debugger: &'a mut Debugger,
+        debug_info: Option<&'a DebugInfo>,
+        reader: Box<dyn Read + Send>,
+        output: Box<dyn Write>,
+    ) -> DapServer<'a> {
+        let (sender, input) = mpsc::channel();
+        let interrupt_handle: Arc<AtomicBool> = debugger.interrupt_handle();
+
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+
+            while let Some(message) = read_message(&mut reader) {
+                if message["command"] == "pause" {
+                    interrupt_handle.store(true, Ordering::Relaxed);
+                }
+
+                if sender.send(message).is_err() {
+                    return;
+                }
+            }
+        });
+
+        DapServer {
+            debugger,
+            debug_info,
+            input,
+            output,
+            seq: 1,
+            breakpoints: HashMap::new(),
+            stop_on_entry: false,
+        }
+    }
+
+    /**
+     * Serve requests until the client disconnects or terminates the program
+     */
+    pub fn run(&mut self) -> Result<(), String> {
+        while let Ok(request) = self.input.recv() {
+            let command = request["command"].as_str().unwrap_or_default().to_string();
+            let arguments = &request["arguments"];
+
+            match command.as_str() {
+                "disconnect" | "terminate" => {
+                    self.respond(&request, Ok(json!({})))?;
+                    return Ok(());
+                }
+                "stepBack" | "reverseContinue" if self.debugger.state.history.is_none() => {
+                    self.respond(&request, Err("History is not enabled".to_string()))?;
+                }
+                "continue" | "next" | "stepIn" | "stepOut" | "stepBack" | "reverseContinue" => {
+                    self.respond(&request, Ok(json!({ "allThreadsContinued": true })))?;
+                    self.resume(&command, arguments["granularity"] == "instruction")?;
+                }
+                "configurationDone" => {
+                    self.respond(&request, Ok(json!({})))?;
+
+                    match self.stop_on_entry {
+                        true => self.send_stopped("entry", json!({}))?,
+                        false => self.resume("continue", false)?,
+                    }
+                }
+                _ => {
+                    let response = self.handle(&command, arguments);
+
+                    self.respond(&request, response)?;
+
+                    if command == "initialize" {
+                        self.send_event("initialized", json!({}))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle(&mut self, command: &str, arguments: &Value) -> Result<Value, String> {
+        match command {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsConditionalBreakpoints": true,
+                "supportsSetVariable": true,
+                "supportsSteppingGranularity": true,
+                "supportsStepBack": self.debugger.state.history.is_some(),
+                "supportsTerminateRequest": true,
+            })),
+            "launch" | "attach" => {
+                self.stop_on_entry = arguments["stopOnEntry"].as_bool().unwrap_or(false);
+
+                Ok(json!({}))
+            }
+            "setBreakpoints" => self.set_breakpoints(arguments),
+            "setExceptionBreakpoints" => Ok(json!({})),
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "CAL" }] })),
+            "stackTrace" => Ok(self.stack_trace()),
+            "scopes" => Ok(json!({
+                "scopes": [
+                    { "name": "Registers", "variablesReference": REGISTERS_REFERENCE, "expensive": false },
+                    { "name": "Flags", "variablesReference": FLAGS_REFERENCE, "expensive": false },
+                ]
+            })),
+            "variables" => Ok(self.variables(arguments["variablesReference"].as_u64().unwrap_or_default())),
+            "setVariable" => self.set_variable(arguments),
+            "pause" => Ok(json!({})),
+            _ => Err(format!("Unsupported request \"{}\"", command)),
+        }
+    }
+
+    fn set_breakpoints(&mut self, arguments: &Value) -> Result<Value, String> {
+        let path = arguments["source"]["path"].as_str().ok_or("No source path provided")?;
+
+        for id in self.breakpoints.remove(path).unwrap_or_default() {
+            self.debugger.remove(id);
+        }
+
+        let file = self.find_source(path);
+        let mut ids = Vec::new();
+        let mut breakpoints = Vec::new();
+
+        for breakpoint in arguments["breakpoints"].as_array().into_iter().flatten() {
+            let line = breakpoint["line"].as_u64().unwrap_or_default() as usize;
+
+            let address = match (self.debug_info, file) {
+                (None, _) => Err("Breakpoints on source lines require debug info".to_string()),
+                (_, None) => Err(format!("No code was assembled from {}", path)),
+                (Some(debug_info), Some(file)) => debug_info
+                    .line_address(file, line)
+                    .ok_or(format!("No instructions were assembled from line {}", line)),
+            };
+
+            let condition = match breakpoint["condition"]
+                .as_str()
+                .filter(|condition| !condition.is_empty())
+            {
+                Some(condition) => Condition::parse(condition).map(Some),
+                None => Ok(None),
+            };
+
+            match address.and_then(|address| Ok((address, condition?))) {
+                Ok((address, condition)) => {
+                    let id = self.debugger.add_breakpoint(address, condition);
+
+                    ids.push(id);
+                    breakpoints.push(json!({ "id": id, "verified": true, "line": line }));
+                }
+                Err(message) => breakpoints.push(json!({ "verified": false, "line": line, "message": message })),
+            }
+        }
+
+        self.breakpoints.insert(path.to_string(), ids);
+
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    /**
+     * The index of the debug info file a client's path refers to. Clients use absolute paths, while debug info has
+     * the paths given to the assembler.
+     */
+    fn find_source(&self, path: &str) -> Option<usize> {
+        let debug_info = self.debug_info?;
+
+        debug_info
+            .files
+            .iter()
+            .position(|file| source_path(file) == path)
+            .or_else(|| debug_info.find_file(path))
+            .or_else(|| {
+                debug_info.files.iter().position(|file| {
+                    let file = file.trim_start_matches("./");
+
+                    path.ends_with(file) && path[..path.len() - file.len()].ends_with('/')
+                })
+            })
+    }
+
+    fn stack_trace(&self) -> Value {
+        let state = &self.debugger.state;
+
+        // The PC, then the CALL instruction of each subroutine on the call stack
+        let addresses = std::iter::once(state.pc).chain(
+            state.call_stack[..state.call_stack_pointer as usize]
+                .iter()
+                .rev()
+                .copied(),
+        );
+
+        let frames: Vec<Value> = addresses
+            .flat_map(|address| self.frames_at(address))
+            .enumerate()
+            .map(|(id, mut frame)| {
+                frame["id"] = json!(id);
+                frame
+            })
+            .collect();
+
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    /**
+     * The stack frames for an address, innermost first: the line the statement was written at, then the line of each
+     * macro invocation it was expanded from
+     */
+    fn frames_at(&self, address: u16) -> Vec<Value> {
+        let name = self.describe_address(address);
+
+        let source_frames: Vec<&SourceFrame> = self
+            .debug_info
+            .and_then(|debug_info| debug_info.statement_at(address))
+            .map(|statement| {
+                statement
+                    .backtrace
+                    .iter()
+                    .rev()
+                    .filter(|frame| frame.kind != FrameKind::Include)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if source_frames.is_empty() {
+            return vec![json!({
+                "name": name,
+                "line": 0,
+                "column": 0,
+                "instructionPointerReference": format!("0x{:04X}", address),
+            })];
+        }
+
+        let outermost = source_frames.len() - 1;
+
+        source_frames
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| {
+                let file = &self.debug_info.unwrap().files[frame.file];
+                let file_name = Path::new(file)
+                    .file_name()
+                    .map_or(file.clone(), |name| name.to_string_lossy().into_owned());
+
+                json!({
+                    "name": match index == outermost {
+                        true => name.clone(),
+                        false => format!("{} (macro expansion)", name),
+                    },
+                    "source": {
+                        "name": file_name,
+                        "path": source_path(file),
+                    },
+                    "line": frame.line,
+                    "column": frame.column,
+                    "instructionPointerReference": format!("0x{:04X}", address),
+                    "presentationHint": match index == outermost {
+                        true => "normal",
+                        false => "subtle",
+                    },
+                })
+            })
+            .collect()
+    }
+
+    // An address with the closest preceding label, e.g. `.FIB+3`
+    fn describe_address(&self, address: u16) -> String {
+        match self.debug_info.and_then(|debug_info| debug_info.label_before(address)) {
+            Some((label, label_address)) if *label_address == address => format!(".{}", label),
+            Some((label, label_address)) => format!(".{}+{}", label, address - label_address),
+            None => format!("{:04X}", address),
+        }
+    }
+
+    fn variables(&self, reference: u64) -> Value {
+        let state = &self.debugger.state;
+
+        let variables: Vec<Value> = match reference {
+            REGISTERS_REFERENCE => state
+                .registers
+                .iter()
+                .enumerate()
+                .map(|(register, value)| (format!("R{}", register), *value))
+                .chain(std::iter::once(("PC".to_string(), state.pc)))
+                .map(|(name, value)| {
+                    json!({
+                        "name": name,
+                        "value": format!("{} (0x{:04X})", value, value),
+                        "variablesReference": 0,
+                    })
+                })
+                .collect(),
+            FLAGS_REFERENCE => FLAGS
+                .iter()
+                .map(|(name, flag)| {
+                    json!({
+                        "name": name,
+                        "value": state.flags.contains(*flag).to_string(),
+                        "type": "bool",
+                        "variablesReference": 0,
+                    })
+                })
+                .chain(
+                    state
+                        .fault
+                        .iter()
+                        .map(|fault| json!({ "name": "Fault", "value": fault, "variablesReference": 0 })),
+                )
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        json!({ "variables": variables })
+    }
+
+    fn set_variable(&mut self, arguments: &Value) -> Result<Value, String> {
+        let name = arguments["name"].as_str().unwrap_or_default();
+        let value = arguments["value"].as_str().unwrap_or_default().trim();
+        let state = &mut self.debugger.state;
+
+        match arguments["variablesReference"].as_u64() {
+            Some(REGISTERS_REFERENCE) => {
+                let number = parse_number(value)?;
+
+                match name {
+                    "PC" => state.pc = number,
+                    _ => {
+                        let register = name
+                            .strip_prefix('R')
+                            .and_then(|register| register.parse::<usize>().ok())
+                            .filter(|register| *register < state.registers.len())
+                            .ok_or(format!("Unrecognized register {}", name))?;
+
+                        state.registers[register] = number;
+                    }
+                }
+
+                Ok(json!({ "value": format!("{} (0x{:04X})", number, number) }))
+            }
+            Some(FLAGS_REFERENCE) => {
+                let (_, flag) = FLAGS
+                    .iter()
+                    .find(|(flag_name, _)| *flag_name == name)
+                    .ok_or(format!("Unrecognized flag {}", name))?;
+
+                let set = match value {
+                    "true" | "1" => true,
+                    "false" | "0" => false,
+                    _ => return Err(format!("Invalid flag value \"{}\", expected true or false", value)),
+                };
+
+                state.flags.set(*flag, set);
+
+                Ok(json!({ "value": set.to_string() }))
+            }
+            _ => Err(format!("{} can't be changed", name)),
+        }
+    }
+
+    /**
+     * Run the program for a resume request and report why it stopped.
+     *
+     * Stepping into (`stepIn`) stops once the line being executed changes, including lines inside macros and
+     * subroutines. Stepping over (`next`) stops once the line changes with macro expansions collapsed and without
+     * entering subroutines. Stepping out (`stepOut`) stops once the current subroutine returns.
+     */
+    fn resume(&mut self, command: &str, instruction_granularity: bool) -> Result<(), String> {
+        let already_faulted = self.debugger.state.fault.is_some();
+        let depth = self.debugger.state.call_stack_pointer;
+
+        let stop_reason = match command {
+            "continue" => self.debugger.continue_execution(),
+            "stepBack" => self.debugger.step_back(),
+            "reverseContinue" => self.debugger.reverse_continue(),
+            "stepOut" => self.step_while(|server| server.debugger.state.call_stack_pointer >= depth),
+            _ if instruction_granularity => self.debugger.step(),
+            "stepIn" => {
+                let start = self.location(false);
+
+                self.step_while(|server| server.location(false).is_some_and(|location| Some(location) == start))
+            }
+            _ => {
+                let start = self.location(true);
+
+                self.step_while(|server| {
+                    server.debugger.state.call_stack_pointer > depth
+                        || server.location(true).is_some_and(|location| Some(location) == start)
+                })
+            }
+        };
+
+        match stop_reason {
+            StopReason::Halted => {
+                let exit_code = self.debugger.state.exit_status.unwrap_or(0);
+
+                self.send_event("exited", json!({ "exitCode": exit_code }))?;
+                self.send_event("terminated", json!({}))
+            }
+            // The program can't run any further once it has faulted and the fault has been reported
+            StopReason::Faulted if already_faulted => self.send_event("terminated", json!({})),
+            StopReason::Faulted => {
+                let fault = self.debugger.state.fault.clone().unwrap_or_default();
+
+                self.send_stopped("exception", json!({ "description": "Fault", "text": fault }))
+            }
+            StopReason::Step => self.send_stopped("step", json!({})),
+            StopReason::Breakpoint(id) => self.send_stopped("breakpoint", json!({ "hitBreakpointIds": [id] })),
+            StopReason::Watchpoint(hit) => self.send_stopped(
+                "data breakpoint",
+                json!({ "text": format!("{:04X}: {:04X} -> {:04X}", hit.address, hit.old_value, hit.new_value) }),
+            ),
+            StopReason::Interrupted => self.send_stopped("pause", json!({})),
+            StopReason::HistoryExhausted => self.send_stopped(
+                "step",
+                json!({ "description": "Reached the start of recorded history" }),
+            ),
+        }
+    }
+
+    /**
+     * Step until the condition no longer holds, stopping early at breakpoints, watchpoints, when the program halts or
+     * when the client pauses
+     */
+    fn step_while(&mut self, condition: impl Fn(&DapServer) -> bool) -> StopReason {
+        let interrupt_handle = self.debugger.interrupt_handle();
+
+        interrupt_handle.store(false, Ordering::Relaxed);
+
+        loop {
+            match self.debugger.step() {
+                StopReason::Step => {}
+                stop_reason => return stop_reason,
+            }
+
+            if !condition(self) {
+                return StopReason::Step;
+            }
+
+            if let Some(id) = self.debugger.breakpoint_at_pc() {
+                return StopReason::Breakpoint(id);
+            }
+
+            if interrupt_handle.swap(false, Ordering::Relaxed) {
+                return StopReason::Interrupted;
+            }
+        }
+    }
+
+    // The source line the PC was assembled from, with macro expansions collapsed if `outermost`
+    fn location(&self, outermost: bool) -> Option<SourceFrame> {
+        let statement = self.debug_info?.statement_at(self.debugger.state.pc)?;
+
+        Some(match outermost {
+            true => statement.outermost_location().clone(),
+            false => statement.location().clone(),
+        })
+    }
+
+    fn respond(&mut self, request: &Value, response: Result<Value, String>) -> Result<(), String> {
+        let mut message = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": response.is_ok(),
+        });
+
+        match response {
+            Ok(body) => message["body"] = body,
+            Err(error) => message["message"] = json!(error),
+        }
+
+        self.send(message)
+    }
+
+    fn send_stopped(&mut self, reason: &str, mut body: Value) -> Result<(), String> {
+        body["reason"] = json!(reason);
+        body["threadId"] = json!(THREAD_ID);
+        body["allThreadsStopped"] = json!(true);
+
+        self.send_event("stopped", body)
+    }
+
+    fn send_event(&mut self, event: &str, body: Value) -> Result<(), String> {
+        self.send(json!({ "type": "event", "event": event, "body": body }))
+    }
+
+    fn send(&mut self, mut message: Value) -> Result<(), String> {
+        message["seq"] = json!(self.seq);
+        self.seq += 1;
+
+        let body = message.to_string();
+
+        write!(self.output, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+            .and_then(|_| self.output.flush())
+            .map_err(|e| format!("Could not write to the debug adapter client: {}", e))
+    }
+}
+
+/**
+ * Read a message, which is a JSON body preceded by headers including its length, returning None once the connection
+ * is closed
+ */
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+
+        match line.trim_end() {
+            "" => break,
+            header => {
+                if let Some(value) = header.strip_prefix("Content-Length:") {
+                    length = value.trim().parse().ok();
+                }
+            }
+        }
+    }
+
+    let mut body = vec![0; length?];
+
+    reader.read_exact(&mut body).ok()?;
+
+    serde_json::from_slice(&body).ok()
+}
+
+// The absolute path of a file from the debug info, if it exists
+fn source_path(file: &str) -> String {
+    fs::canonicalize(file).map_or(file.to_string(), |path| path.to_string_lossy().into_owned())
+}
@@ -0,0 +1,507 @@
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+use shared::BranchConditions;
+
+use super::{Debugger, StopReason, WatchKind};
+
+// R0-R7, the PC and the flags
+const REGISTER_COUNT: usize = 10;
+const PC_REGISTER: usize = 8;
+const FLAGS_REGISTER: usize = 9;
+
+// Sent by GDB to interrupt a running program
+const INTERRUPT: u8 = 0x03;
+
+// Signals reported in stop replies
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.cal.cpu">
+    <flags id="cal_flags" size="2">
+      <field name="p" start="0" end="0"/>
+      <field name="z" start="1" end="1"/>
+      <field name="n" start="2" end="2"/>
+      <field name="c" start="3" end="3"/>
+      <field name="v" start="4" end="4"/>
+    </flags>
+    <reg name="r0" bitsize="16" type="int16" regnum="0"/>
+    <reg name="r1" bitsize="16" type="int16"/>
+    <reg name="r2" bitsize="16" type="int16"/>
+    <reg name="r3" bitsize="16" type="int16"/>
+    <reg name="r4" bitsize="16" type="int16"/>
+    <reg name="r5" bitsize="16" type="int16"/>
+    <reg name="r6" bitsize="16" type="int16"/>
+    <reg name="r7" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="32" type="code_ptr"/>
+    <reg name="flags" bitsize="16" type="cal_flags"/>
+  </feature>
+</target>
+"#;
+
+/**
+ * A GDB remote serial protocol server, which drives a `Debugger` on behalf of a GDB client.
+ *
+ * GDB addresses bytes while CAL addresses words, so byte address `A` is the high (even `A`) or low (odd `A`) byte of
+ * word `A / 2`, and the PC is reported as a byte address (32 bits wide, as byte addresses go up to 0x1FFFE). All values
+ * are big endian.
+ */
+pub struct GdbServer<'a> {
+    debugger: &'a mut Debugger,
+    input: Receiver<u8>,
+    output: Box<dyn Write>,
+    // The debugger breakpoint or watchpoint for each GDB (type, address, length)
+    points: HashMap<(u8, u32, u32), usize>,
+}
+
+impl<'a> GdbServer<'a> {
+    /**
+     * Bytes are read from `reader` on a background thread, so that GDB can interrupt a running program
+     */
+    pub fn new(debugger: &'a mut Debugger, mut reader: Box<dyn Read + Send>, output: Box<dyn Write>) -> GdbServer<'a> {
+        let (sender, input) = mpsc::channel();
+        let interrupt_handle: Arc<AtomicBool> = debugger.interrupt_handle();
+
+        thread::spawn(move || {
+            let mut buffer = [0u8; 1024];
+
+            while let Ok(count @ 1..) = reader.read(&mut buffer) {
+                for byte in &buffer[..count] {
+                    match *byte {
+                        INTERRUPT => interrupt_handle.store(true, Ordering::Relaxed),
+                        byte => {
+                            if sender.send(byte).is_err() {
+                                return;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        GdbServer {
+            debugger,
+            input,
+            output,
+            points: HashMap::new(),
+        }
+    }
+
+    /**
+     * Serve packets until GDB detaches, kills the program or disconnects
+     */
+    pub fn run(&mut self) -> Result<(), String> {
+        while let Some(packet) = self.receive()? {
+            let response = match packet.as_str() {
+                "D" | "k" => {
+                    self.send("OK")?;
+                    return Ok(());
+                }
+                _ => self.handle(&packet),
+            };
+
+            self.send(&response)?;
+        }
+
+        Ok(())
+    }
+
+    // The next packet's contents, or None if the connection was closed
+    fn receive(&mut self) -> Result<Option<String>, String> {
+        loop {
+            match self.input.recv() {
+                Ok(b'$') => {}
+                // Acknowledgements, and anything else outside of a packet
+                Ok(_) => continue,
+                Err(_) => return Ok(None),
+            }
+
+            let mut packet = Vec::new();
+
+            loop {
+                match self.input.recv() {
+                    Ok(b'#') => break,
+                    Ok(byte) => packet.push(byte),
+                    Err(_) => return Ok(None),
+                }
+            }
+
+            let checksum: Vec<u8> = (0..2).filter_map(|_| self.input.recv().ok()).collect();
+            let expected = format!("{:02x}", checksum_of(&packet));
+
+            if !checksum.eq_ignore_ascii_case(expected.as_bytes()) {
+                self.write(b"-")?;
+                continue;
+            }
+
+            self.write(b"+")?;
+
+            return Ok(Some(unescape(&packet)));
+        }
+    }
+
+    fn send(&mut self, response: &str) -> Result<(), String> {
+        let escaped = escape(response);
+        let packet = format!("${}#{:02x}", escaped, checksum_of(escaped.as_bytes()));
+
+        self.write(packet.as_bytes())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.output
+            .write_all(bytes)
+            .and_then(|_| self.output.flush())
+            .map_err(|e| format!("Could not write to GDB: {}", e))
+    }
+
+    fn handle(&mut self, packet: &str) -> String {
+        let (command, args) = packet.split_at(packet.chars().next().map_or(0, char::len_utf8));
+
+        let response = match command {
+            "?" => Ok(format!("S{:02x}", SIGTRAP)),
+            "g" => Ok(self.read_registers()),
+            "G" => self.write_registers(args),
+            "p" => self.read_register(args),
+            "P" => self.write_register(args),
+            "m" => self.read_memory(args),
+            "M" => self.write_memory(args),
+            "s" | "c" => self.resume(command, args),
+            "b" if args == "s" || args == "c" => Ok(self.reverse(args)),
+            "Z" => self.insert_point(args),
+            "z" => self.remove_point(args),
+            "H" => Ok("OK".to_string()),
+            "T" => Ok("OK".to_string()),
+            "q" => Ok(self.query(args)),
+            _ => Ok(String::new()),
+        };
+
+        response.unwrap_or_else(|e| e)
+    }
+
+    fn query(&self, query: &str) -> String {
+        if query.starts_with("Supported") {
+            return "PacketSize=4000;qXfer:features:read+;swbreak+;hwbreak+;ReverseStep+;ReverseContinue+".to_string();
+        }
+
+        if let Some(range) = query.strip_prefix("Xfer:features:read:target.xml:") {
+            return match parse_address_and_length(range) {
+                Ok((offset, length)) => {
+                    let start = (offset as usize).min(TARGET_XML.len());
+                    let end = (start + length as usize).min(TARGET_XML.len());
+
+                    match end == TARGET_XML.len() {
+                        true => format!("l{}", &TARGET_XML[start..end]),
+                        false => format!("m{}", &TARGET_XML[start..end]),
+                    }
+                }
+                Err(e) => e,
+            };
+        }
+
+        match query {
+            "Attached" => "1".to_string(),
+            "C" => "QC1".to_string(),
+            "fThreadInfo" => "m1".to_string(),
+            "sThreadInfo" => "l".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn registers(&self) -> [u32; REGISTER_COUNT] {
+        let state = &self.debugger.state;
+        let mut registers = [0; REGISTER_COUNT];
+
+        for (register, value) in state.registers.iter().enumerate() {
+            registers[register] = *value as u32;
+        }
+
+        registers[PC_REGISTER] = state.pc as u32 * 2;
+        registers[FLAGS_REGISTER] = state.flags.bits() as u32;
+
+        registers
+    }
+
+    fn set_register(&mut self, register: usize, value: u32) -> Result<(), String> {
+        let state = &mut self.debugger.state;
+
+        match register {
+            0..=7 => state.registers[register] = value as u16,
+            PC_REGISTER => state.pc = u16::try_from(value / 2).map_err(|_| "E01")?,
+            FLAGS_REGISTER => state.flags = BranchConditions::from_bits_truncate(value as u16),
+            _ => return Err("E01".to_string()),
+        }
+
+        Ok(())
+    }
+
+    fn read_registers(&self) -> String {
+        self.registers()
+            .iter()
+            .enumerate()
+            .map(|(register, value)| encode_register(register, *value))
+            .collect()
+    }
+
+    fn write_registers(&mut self, values: &str) -> Result<String, String> {
+        let mut values = decode_hex(values)?;
+
+        if values.len() != (0..REGISTER_COUNT).map(register_size).sum::<usize>() {
+            return Err("E01".to_string());
+        }
+
+        for register in 0..REGISTER_COUNT {
+            let value = values.drain(..register_size(register)).collect::<Vec<u8>>();
+
+            self.set_register(register, decode_register(&value))?;
+        }
+
+        Ok("OK".to_string())
+    }
+
+    fn read_register(&self, register: &str) -> Result<String, String> {
+        let register = usize::from_str_radix(register, 16).map_err(|_| "E01")?;
+
+        self.registers()
+            .get(register)
+            .map(|value| encode_register(register, *value))
+            .ok_or("E01".to_string())
+    }
+
+    fn write_register(&mut self, args: &str) -> Result<String, String> {
+        let (register, value) = args.split_once('=').ok_or("E01")?;
+        let register = usize::from_str_radix(register, 16).map_err(|_| "E01")?;
+        let value = decode_hex(value)?;
+
+        if register >= REGISTER_COUNT || value.len() != register_size(register) {
+            return Err("E01".to_string());
+        }
+
+        self.set_register(register, decode_register(&value))?;
+
+        Ok("OK".to_string())
+    }
+
+    /**
+     * Memory is read directly rather than through devices, so reading it has no side effects
+     */
+    fn read_memory(&self, args: &str) -> Result<String, String> {
+        let (address, length) = parse_address_and_length(args)?;
+
+        Ok((address..address.saturating_add(length))
+            .take_while(|address| *address < 0x20000)
+            .map(|address| {
+                let word = self.debugger.state.memory[(address / 2) as usize];
+
+                format!("{:02x}", word.to_be_bytes()[(address % 2) as usize])
+            })
+            .collect())
+    }
+
+    fn write_memory(&mut self, args: &str) -> Result<String, String> {
+        let (range, data) = args.split_once(':').ok_or("E01")?;
+        let (address, length) = parse_address_and_length(range)?;
+        let data = decode_hex(data)?;
+
+        if data.len() != length as usize || address as usize + data.len() > 0x20000 {
+            return Err("E01".to_string());
+        }
+
+        let state = &mut self.debugger.state;
+
+        for (offset, byte) in data.iter().enumerate() {
+            let address = address as usize + offset;
+            let word_address = address / 2;
+            let mut bytes = state.memory[word_address].to_be_bytes();
+
+            bytes[address % 2] = *byte;
+            state.memory[word_address] = u16::from_be_bytes(bytes);
+            state.invalidate_decoded(word_address as u16);
+        }
+
+        Ok("OK".to_string())
+    }
+
+    fn resume(&mut self, command: &str, address: &str) -> Result<String, String> {
+        if !address.is_empty() {
+            let address = u32::from_str_radix(address, 16).map_err(|_| "E01")?;
+
+            self.debugger.state.pc = (address / 2) as u16;
+        }
+
+        let stop_reason = match command {
+            "s" => self.debugger.step(),
+            _ => self.debugger.continue_execution(),
+        };
+
+        Ok(self.stop_reply(stop_reason))
+    }
+
+    fn reverse(&mut self, command: &str) -> String {
+        if self.debugger.state.history.is_none() {
+            return "E01".to_string();
+        }
+
+        let stop_reason = match command {
+            "s" => self.debugger.step_back(),
+            _ => self.debugger.reverse_continue(),
+        };
+
+        self.stop_reply(stop_reason)
+    }
+
+    fn stop_reply(&self, stop_reason: StopReason) -> String {
+        match stop_reason {
+            StopReason::Step | StopReason::Breakpoint(_) => format!("S{:02x}", SIGTRAP),
+            StopReason::Interrupted => format!("S{:02x}", SIGINT),
+            StopReason::Faulted => format!("S{:02x}", SIGILL),
+            StopReason::Halted => format!("W{:02x}", self.debugger.state.exit_status.unwrap_or(0)),
+            StopReason::HistoryExhausted => format!("T{:02x}replaylog:begin;", SIGTRAP),
+            StopReason::Watchpoint(hit) => {
+                let kind = self
+                    .debugger
+                    .watchpoints()
+                    .iter()
+                    .find(|watchpoint| watchpoint.id == hit.id)
+                    .map_or(WatchKind::ReadWrite, |watchpoint| watchpoint.kind);
+
+                let name = match kind {
+                    WatchKind::Write => "watch",
+                    WatchKind::Read => "rwatch",
+                    WatchKind::ReadWrite => "awatch",
+                };
+
+                format!("T{:02x}{}:{:x};", SIGTRAP, name, hit.address as u32 * 2)
+            }
+        }
+    }
+
+    /**
+     * Insert a breakpoint (types 0 and 1, which are treated the same) or a watchpoint (types 2-4)
+     */
+    fn insert_point(&mut self, args: &str) -> Result<String, String> {
+        let (kind, address, length) = parse_point(args)?;
+
+        if self.points.contains_key(&(kind, address, length)) {
+            return Ok("OK".to_string());
+        }
+
+        let start = (address / 2) as u16;
+        let end = (address.saturating_add(length.max(1)) - 1).min(0x1FFFF) / 2;
+
+        let id = match kind {
+            0 | 1 => self.debugger.add_breakpoint(start, None),
+            2 => self.debugger.add_watchpoint(start..=end as u16, WatchKind::Write),
+            3 => self.debugger.add_watchpoint(start..=end as u16, WatchKind::Read),
+            4 => self.debugger.add_watchpoint(start..=end as u16, WatchKind::ReadWrite),
+            _ => return Ok(String::new()),
+        };
+
+        self.points.insert((kind, address, length), id);
+
+        Ok("OK".to_string())
+    }
+
+    fn remove_point(&mut self, args: &str) -> Result<String, String> {
+        if let Some(id) = self.points.remove(&parse_point(args)?) {
+            self.debugger.remove(id);
+        }
+
+        Ok("OK".to_string())
+    }
+}
+
+fn checksum_of(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |checksum, byte| checksum.wrapping_add(*byte))
+}
+
+// Bytes which must be escaped in packets, by sending '}' followed by the byte XORed with 0x20
+fn escape(response: &str) -> String {
+    response
+        .chars()
+        .flat_map(|character| match character {
+            '$' | '#' | '}' | '*' => vec!['}', (character as u8 ^ 0x20) as char],
+            _ => vec![character],
+        })
+        .collect()
+}
+
+fn unescape(packet: &[u8]) -> String {
+    let mut bytes = Vec::new();
+    let mut iter = packet.iter();
+
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'}' => bytes.extend(iter.next().map(|byte| byte ^ 0x20)),
+            _ => bytes.push(*byte),
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// The number of bytes GDB expects for a register, all are 16 bits except the PC which holds byte addresses
+fn register_size(register: usize) -> usize {
+    match register {
+        PC_REGISTER => 4,
+        _ => 2,
+    }
+}
+
+fn encode_register(register: usize, value: u32) -> String {
+    format!("{:0width$x}", value, width = register_size(register) * 2)
+}
+
+// A big endian register value of any size
+fn decode_register(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |value, byte| value << 8 | *byte as u32)
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, String> {
+    // Packets may contain anything, so only the raw bytes are safe to split up
+    let hex = hex.as_bytes();
+
+    if !hex.len().is_multiple_of(2) {
+        return Err("E01".to_string());
+    }
+
+    hex.chunks(2)
+        .map(|pair| match (hex_digit(pair[0]), hex_digit(pair[1])) {
+            (Some(high), Some(low)) => Ok(high << 4 | low),
+            _ => Err("E01".to_string()),
+        })
+        .collect()
+}
+
+fn hex_digit(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+// Parse `ADDRESS,LENGTH` in hex
+fn parse_address_and_length(args: &str) -> Result<(u32, u32), String> {
+    let (address, length) = args.split_once(',').ok_or("E01")?;
+
+    Ok((
+        u32::from_str_radix(address, 16).map_err(|_| "E01")?,
+        u32::from_str_radix(length, 16).map_err(|_| "E01")?,
+    ))
+}
+
+// Parse the `TYPE,ADDRESS,KIND` arguments of Z and z packets
+fn parse_point(args: &str) -> Result<(u8, u32, u32), String> {
+    let (kind, rest) = args.split_once(',').ok_or("E01")?;
+    let (address, length) = parse_address_and_length(rest.split(';').next().unwrap_or_default())?;
+
+    Ok((kind.parse().map_err(|_| "E01")?, address, length))
+}
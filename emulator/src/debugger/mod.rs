@@ -0,0 +1,397 @@
+mod condition;
+pub mod dap;
+pub mod gdb;
+mod watchpoint;
+
+pub use condition::{parse_number, Condition};
+pub use watchpoint::{MemoryAccess, WatchKind, Watchpoint, WatchpointHit};
+
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    ops::RangeInclusive,
+    os::unix::net::UnixListener,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use shared::debug_info::DebugInfo;
+
+use crate::{history::History, state::State};
+
+#[derive(Clone, Debug)]
+pub struct Breakpoint {
+    pub id: usize,
+    pub address: u16,
+    pub condition: Option<Condition>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum StopReason {
+    // The program executed a HLT instruction
+    Halted,
+    // The program was stopped by a fault, described by `State::fault`
+    Faulted,
+    // A single step completed without anything else of note happening
+    Step,
+    Breakpoint(usize),
+    Watchpoint(WatchpointHit),
+    // Execution was interrupted through the handle returned by `Debugger::interrupt_handle`
+    Interrupted,
+    // Execution was stepped backwards to the oldest recorded instruction
+    HistoryExhausted,
+}
+
+/**
+ * Drives execution of a program, stopping at breakpoints and watchpoints
+ */
+pub struct Debugger {
+    pub state: State,
+    breakpoints: Vec<Breakpoint>,
+    next_id: usize,
+    interrupt_requested: Arc<AtomicBool>,
+}
+
+impl Debugger {
+    pub fn new(state: State) -> Debugger {
+        Debugger {
+            state,
+            breakpoints: Vec::new(),
+            next_id: 1,
+            interrupt_requested: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /**
+     * A flag which can be set (e.g. from a signal handler or another thread) to stop a running `continue_execution`
+     */
+    pub fn interrupt_handle(&self) -> Arc<AtomicBool> {
+        self.interrupt_requested.clone()
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16, condition: Option<Condition>) -> usize {
+        let id = self.allocate_id();
+
+        self.breakpoints.push(Breakpoint { id, address, condition });
+
+        id
+    }
+
+    pub fn add_watchpoint(&mut self, range: RangeInclusive<u16>, kind: WatchKind) -> usize {
+        let id = self.allocate_id();
+
+        self.state.watchpoints.push(Watchpoint { id, range, kind });
+
+        id
+    }
+
+    /**
+     * Remove the breakpoint or watchpoint with the given id, returning whether it existed
+     */
+    pub fn remove(&mut self, id: usize) -> bool {
+        let count = self.breakpoints.len() + self.state.watchpoints.len();
+
+        self.breakpoints.retain(|breakpoint| breakpoint.id != id);
+        self.state.watchpoints.retain(|watchpoint| watchpoint.id != id);
+
+        count != self.breakpoints.len() + self.state.watchpoints.len()
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.state.watchpoints
+    }
+
+    /**
+     * Execute a single instruction
+     */
+    pub fn step(&mut self) -> StopReason {
+        if self.state.halt {
+            return self.halt_reason();
+        }
+
+        crate::step(&mut self.state);
+
+        if let Some(hit) = self.state.watchpoint_hit.take() {
+            return StopReason::Watchpoint(hit);
+        }
+
+        match self.state.halt {
+            true => self.halt_reason(),
+            false => StopReason::Step,
+        }
+    }
+
+    fn halt_reason(&self) -> StopReason {
+        match self.state.fault {
+            Some(_) => StopReason::Faulted,
+            None => StopReason::Halted,
+        }
+    }
+
+    /**
+     * Execute until the program halts, a watchpoint is triggered, or a breakpoint whose condition holds is reached
+     */
+    pub fn continue_execution(&mut self) -> StopReason {
+        self.interrupt_requested.store(false, Ordering::Relaxed);
+
+        loop {
+            if self.interrupt_requested.swap(false, Ordering::Relaxed) {
+                return StopReason::Interrupted;
+            }
+
+            match self.step() {
+                StopReason::Step => {}
+                stop_reason => return stop_reason,
+            }
+
+            if let Some(id) = self.breakpoint_at_pc() {
+                return StopReason::Breakpoint(id);
+            }
+        }
+    }
+
+    /**
+     * Start recording history so that execution can be stepped backwards, keeping at most `limit` instructions
+     */
+    pub fn enable_history(&mut self, limit: usize) {
+        if self.state.history.is_none() {
+            self.state.history = Some(History::new(limit));
+        }
+    }
+
+    /**
+     * Undo the most recently executed instruction
+     */
+    pub fn step_back(&mut self) -> StopReason {
+        match History::undo(&mut self.state) {
+            true => StopReason::Step,
+            false => StopReason::HistoryExhausted,
+        }
+    }
+
+    /**
+     * Step backwards until a breakpoint whose condition holds is reached, an instruction which wrote to an address
+     * covered by a write watchpoint is undone, or history is exhausted
+     */
+    pub fn reverse_continue(&mut self) -> StopReason {
+        self.interrupt_requested.store(false, Ordering::Relaxed);
+
+        loop {
+            if self.interrupt_requested.swap(false, Ordering::Relaxed) {
+                return StopReason::Interrupted;
+            }
+
+            let watchpoint_hit = self.last_step_watchpoint_hit();
+
+            if let StopReason::HistoryExhausted = self.step_back() {
+                return StopReason::HistoryExhausted;
+            }
+
+            if let Some(hit) = watchpoint_hit {
+                return StopReason::Watchpoint(hit);
+            }
+
+            if let Some(id) = self.breakpoint_at_pc() {
+                return StopReason::Breakpoint(id);
+            }
+        }
+    }
+
+    /**
+     * Step backwards until just before the most recent instruction which wrote to an address
+     */
+    pub fn run_back_to_write(&mut self, address: u16) -> StopReason {
+        loop {
+            let wrote = self
+                .state
+                .history
+                .as_ref()
+                .is_some_and(|history| history.last_step_wrote(address));
+
+            if let StopReason::HistoryExhausted = self.step_back() {
+                return StopReason::HistoryExhausted;
+            }
+
+            if wrote {
+                return StopReason::Step;
+            }
+        }
+    }
+
+    /**
+     * Record the current position in history so it can be returned to with `rewind`
+     */
+    pub fn add_checkpoint(&mut self) -> Result<usize, String> {
+        match self.state.history.as_mut() {
+            Some(history) => Ok(history.add_checkpoint()),
+            None => Err("History is not enabled".to_string()),
+        }
+    }
+
+    /**
+     * Step backwards to a checkpoint
+     */
+    pub fn rewind(&mut self, checkpoint_id: usize) -> Result<(), String> {
+        let history = self.state.history.as_ref().ok_or("History is not enabled")?;
+
+        let target = history
+            .checkpoint_position(checkpoint_id)
+            .ok_or(format!("No checkpoint with id {}", checkpoint_id))?;
+
+        if target > history.position() {
+            return Err(format!("Checkpoint {} is ahead of the current position", checkpoint_id));
+        }
+
+        if history.position() - target > history.len() as u64 {
+            return Err(format!(
+                "Checkpoint {} is older than the recorded history",
+                checkpoint_id
+            ));
+        }
+
+        while self.state.history.as_ref().unwrap().position() > target {
+            self.step_back();
+        }
+
+        Ok(())
+    }
+
+    // The write watchpoint that the most recently executed instruction would have triggered, if any
+    fn last_step_watchpoint_hit(&self) -> Option<WatchpointHit> {
+        let history = self.state.history.as_ref()?;
+
+        history.last_step_writes().iter().find_map(|(address, previous_value)| {
+            self.state
+                .watchpoints
+                .iter()
+                .find(|watchpoint| watchpoint.matches(*address, MemoryAccess::Write))
+                .map(|watchpoint| WatchpointHit {
+                    id: watchpoint.id,
+                    pc: self.state.pc.wrapping_sub(1),
+                    address: *address,
+                    access: MemoryAccess::Write,
+                    old_value: *previous_value,
+                    new_value: self.state.memory[*address as usize],
+                })
+        })
+    }
+
+    /**
+     * The id of the first breakpoint at the current PC whose condition holds
+     */
+    pub fn breakpoint_at_pc(&self) -> Option<usize> {
+        self.breakpoints
+            .iter()
+            .find(|breakpoint| {
+                breakpoint.address == self.state.pc
+                    && breakpoint
+                        .condition
+                        .as_ref()
+                        .is_none_or(|condition| condition.evaluate(&self.state))
+            })
+            .map(|breakpoint| breakpoint.id)
+    }
+
+    fn allocate_id(&mut self) -> usize {
+        self.next_id += 1;
+
+        self.next_id - 1
+    }
+}
+
+/**
+ * Resolve a code location to an address.
+ *
+ * Locations can be an address (`0x1A`, `26`), a label (`.MAIN`) or a source line (`main.asm:12`), the latter two
+ * requiring debug info.
+ */
+pub fn resolve_location(location: &str, debug_info: Option<&DebugInfo>) -> Result<u16, String> {
+    if let Some(label) = location.strip_prefix('.') {
+        return debug_info
+            .ok_or("Resolving labels requires debug info")?
+            .label_address(label)
+            .ok_or(format!("Unrecognized label {}", location));
+    }
+
+    if let Some((file, line)) = location.rsplit_once(':') {
+        let debug_info = debug_info.ok_or("Resolving source lines requires debug info")?;
+
+        let file_index = debug_info
+            .find_file(file)
+            .ok_or(format!("No code was assembled from {}", file))?;
+
+        let line = line.parse().map_err(|_| format!("Invalid line number \"{}\"", line))?;
+
+        return debug_info
+            .line_address(file_index, line)
+            .ok_or(format!("No instructions were assembled from {}", location));
+    }
+
+    parse_number(location)
+}
+
+/**
+ * Resolve a range of memory, either a single location or `START..END` (exclusive)
+ */
+pub fn resolve_range(range: &str, debug_info: Option<&DebugInfo>) -> Result<RangeInclusive<u16>, String> {
+    match range.split_once("..") {
+        Some((start, end)) => {
+            let start = resolve_location(start, debug_info)?;
+            let end = resolve_location(end, debug_info)?;
+
+            if end <= start {
+                return Err(format!("Invalid range {}, the end must be after the start", range));
+            }
+
+            Ok(start..=(end - 1))
+        }
+        None => {
+            let address = resolve_location(range, debug_info)?;
+
+            Ok(address..=address)
+        }
+    }
+}
+
+// The read and write halves of a connection to a debugger frontend
+pub type Connection = (Box<dyn Read + Send>, Box<dyn Write>);
+
+/**
+ * Wait for a single connection from a debugger frontend (named by `client` in messages) on `address`, which is either
+ * a TCP port (`1234`), a TCP address (`127.0.0.1:1234`) or the path of a Unix socket, returning the connection's read
+ * and write halves
+ */
+pub fn listen(address: &str, client: &str) -> Result<Connection, String> {
+    let error = |e: std::io::Error| format!("Could not listen for {} on {}: {}", client, address, e);
+
+    if address.parse::<u16>().is_ok() || address.contains(':') {
+        let address = match address.parse::<u16>() {
+            Ok(port) => format!("127.0.0.1:{}", port),
+            Err(_) => address.to_string(),
+        };
+
+        eprintln!("Waiting for {} to connect to {}", client, address);
+
+        let (stream, _) = TcpListener::bind(&address)
+            .and_then(|listener| listener.accept())
+            .map_err(error)?;
+
+        stream.set_nodelay(true).map_err(error)?;
+
+        return Ok((Box::new(stream.try_clone().map_err(error)?), Box::new(stream)));
+    }
+
+    eprintln!("Waiting for {} to connect to {}", client, address);
+
+    let (stream, _) = UnixListener::bind(address)
+        .and_then(|listener| listener.accept())
+        .map_err(error)?;
+
+    Ok((Box::new(stream.try_clone().map_err(error)?), Box::new(stream)))
+}
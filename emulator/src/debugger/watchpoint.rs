@@ -0,0 +1,70 @@
+use std::{
+    fmt::{Display, Formatter, Result as FormatResult},
+    ops::RangeInclusive,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryAccess {
+    Read,
+    Write,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl WatchKind {
+    pub fn parse(kind: &str) -> Result<WatchKind, String> {
+        match kind {
+            "r" => Ok(WatchKind::Read),
+            "w" => Ok(WatchKind::Write),
+            "rw" => Ok(WatchKind::ReadWrite),
+            _ => Err(format!("Invalid watchpoint kind \"{}\", expected r, w or rw", kind)),
+        }
+    }
+}
+
+impl Display for WatchKind {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        match self {
+            WatchKind::Read => write!(f, "r"),
+            WatchKind::Write => write!(f, "w"),
+            WatchKind::ReadWrite => write!(f, "rw"),
+        }
+    }
+}
+
+/**
+ * Stops execution when the program accesses an address within a range of memory
+ */
+#[derive(Clone, Debug)]
+pub struct Watchpoint {
+    pub id: usize,
+    pub range: RangeInclusive<u16>,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    pub fn matches(&self, address: u16, access: MemoryAccess) -> bool {
+        let kind_matches = match access {
+            MemoryAccess::Read => self.kind != WatchKind::Write,
+            MemoryAccess::Write => self.kind != WatchKind::Read,
+        };
+
+        kind_matches && self.range.contains(&address)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WatchpointHit {
+    pub id: usize,
+    // The address of the instruction which performed the access
+    pub pc: u16,
+    pub address: u16,
+    pub access: MemoryAccess,
+    pub old_value: u16,
+    pub new_value: u16,
+}
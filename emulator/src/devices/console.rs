@@ -0,0 +1,289 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, ErrorKind, Read, Write},
+    sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock},
+    thread,
+};
+
+use super::Device;
+
+pub const STATUS: u16 = 0;
+pub const CONTROL: u16 = 1;
+pub const DATA_IN: u16 = 2;
+pub const DATA_OUT: u16 = 3;
+
+// The number of words the console occupies
+pub const SIZE: u16 = 4;
+
+// Status register bits
+pub const STATUS_DATA_AVAILABLE: u16 = 1 << 0;
+// Input has ended and all of it has been read
+pub const STATUS_END_OF_INPUT: u16 = 1 << 1;
+pub const STATUS_OUTPUT_READY: u16 = 1 << 2;
+
+// Control register bits
+pub const CONTROL_BLOCKING: u16 = 1 << 0;
+
+// The value loaded from DATA_IN when no input is available
+pub const NO_INPUT: u16 = 0xFFFF;
+
+// The host's stdin, shared by every host console so only one thread ever reads it
+static HOST_INPUT: OnceLock<InputQueue> = OnceLock::new();
+
+/**
+ * Input for the console which hasn't been read by the program yet
+ */
+#[derive(Default)]
+pub struct Input {
+    pub bytes: VecDeque<u8>,
+    // Whether the end of input has been reached, no more bytes will be added once this is set
+    pub closed: bool,
+}
+
+/**
+ * A handle to input shared between the console and whatever supplies it, e.g. a thread reading the host's stdin or
+ * the debugger
+ */
+#[derive(Clone, Default)]
+pub struct InputQueue {
+    shared: Arc<(Mutex<Input>, Condvar)>,
+}
+
+impl InputQueue {
+    pub fn lock(&self) -> MutexGuard<'_, Input> {
+        self.shared.0.lock().unwrap()
+    }
+
+    pub fn push(&self, bytes: &[u8]) {
+        self.lock().bytes.extend(bytes);
+        self.shared.1.notify_all();
+    }
+
+    pub fn close(&self) {
+        self.lock().closed = true;
+        self.shared.1.notify_all();
+    }
+
+    // Wait until input is available or has ended
+    fn wait<'a>(&self, mut input: MutexGuard<'a, Input>) -> MutexGuard<'a, Input> {
+        while input.bytes.is_empty() && !input.closed {
+            input = self.shared.1.wait(input).unwrap();
+        }
+
+        input
+    }
+}
+
+/**
+ * A console with a status register, a control register, and data registers for input and output bytes.
+ *
+ * Input either comes from the host's stdin, which is read on a background thread so the program can run while it
+ * waits for input, or only from an input queue which can be appended to while the program runs (e.g. by the
+ * debugger). Output bytes are written to the host's stdout or a file as they are.
+ */
+pub struct Console {
+    input: InputQueue,
+    // Whether input arrives independently of the program, so reads can wait for it
+    host_input: bool,
+    output: Box<dyn Write + Send>,
+    // Why writing output failed, no more output is written once this is set
+    output_error: Option<String>,
+    control: u16,
+}
+
+impl Console {
+    /**
+     * Read input from the host's stdin and write output to the host's stdout. Every host console shares the same
+     * input, the first to read a byte consumes it.
+     */
+    pub fn host() -> Console {
+        let input = HOST_INPUT.get_or_init(read_host_input).clone();
+
+        Console {
+            input,
+            host_input: true,
+            output: Box::new(io::stdout()),
+            output_error: None,
+            control: 0,
+        }
+    }
+
+    /**
+     * Read input only from a queue and write output to the host's stdout
+     */
+    pub fn from_queue(input: InputQueue) -> Console {
+        Console {
+            input,
+            host_input: false,
+            output: Box::new(io::stdout()),
+            output_error: None,
+            control: 0,
+        }
+    }
+
+    /**
+     * Write output somewhere other than the host's stdout, e.g. a buffer the output is checked against
+     */
+    pub fn with_output(mut self, output: Box<dyn Write + Send>) -> Console {
+        self.output = output;
+
+        self
+    }
+
+    /**
+     * Write output to a file instead of the host's stdout
+     */
+    pub fn with_output_file(mut self, path: &str) -> Result<Console, String> {
+        let file = File::create(path).map_err(|e| format!("Could not create {}: {}", path, e))?;
+
+        self.output = Box::new(file);
+
+        Ok(self)
+    }
+
+    fn read_byte(&mut self) -> u16 {
+        // Show a prompt which doesn't end in a newline before waiting for its answer
+        if self.control & CONTROL_BLOCKING != 0 {
+            self.flush();
+        }
+
+        let mut input = self.input.lock();
+
+        // Only host input can be waited for, as nothing else can append to the queue while the program runs
+        if self.host_input && self.control & CONTROL_BLOCKING != 0 {
+            input = self.input.wait(input);
+        }
+
+        match input.bytes.pop_front() {
+            Some(byte) => byte as u16,
+            None => NO_INPUT,
+        }
+    }
+
+    /**
+     * Write a byte of output, flushing at the end of each line. If writing fails, e.g. as stdout is a closed pipe, the
+     * error is recorded and later output is discarded.
+     */
+    fn write_byte(&mut self, byte: u8) {
+        if self.output_error.is_some() {
+            return;
+        }
+
+        if let Err(e) = self.output.write_all(&[byte]) {
+            self.output_error = Some(e.to_string());
+        } else if byte == b'\n' {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.output_error.is_none() {
+            if let Err(e) = self.output.flush() {
+                self.output_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+/**
+ * Start a background thread appending the host's stdin to a queue, closing it once stdin ends
+ */
+fn read_host_input() -> InputQueue {
+    let input = InputQueue::default();
+    let reader_input = input.clone();
+
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buffer = [0u8; 1024];
+
+        loop {
+            match stdin.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(count) => reader_input.push(&buffer[..count]),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => break,
+            }
+        }
+
+        reader_input.close();
+    });
+
+    input
+}
+
+impl Device for Console {
+    fn name(&self) -> &str {
+        "console"
+    }
+
+    fn load(&mut self, offset: u16) -> u16 {
+        match offset {
+            STATUS => {
+                let input = self.input.lock();
+                let mut status = 0;
+
+                if self.output_error.is_none() {
+                    status |= STATUS_OUTPUT_READY;
+                }
+
+                if !input.bytes.is_empty() {
+                    status |= STATUS_DATA_AVAILABLE;
+                } else if input.closed {
+                    status |= STATUS_END_OF_INPUT;
+                }
+
+                status
+            }
+            CONTROL => self.control,
+            DATA_IN => self.read_byte(),
+            _ => 0,
+        }
+    }
+
+    fn store(&mut self, offset: u16, value: u16) {
+        match offset {
+            CONTROL => self.control = value,
+            DATA_OUT => self.write_byte(value as u8),
+            _ => {}
+        }
+    }
+
+    /**
+     * The control register followed by the input which hasn't been read yet, one byte per word. Restored input is
+     * read before any input the console already has.
+     */
+    fn save(&self) -> Vec<u16> {
+        let input = self.input.lock();
+
+        [self.control]
+            .into_iter()
+            .chain(input.bytes.iter().map(|byte| *byte as u16))
+            .collect()
+    }
+
+    fn restore(&mut self, data: &[u16]) -> Result<(), String> {
+        let (control, bytes) = data.split_first().ok_or("Invalid console state")?;
+        let mut input = self.input.lock();
+
+        self.control = *control;
+
+        for byte in bytes.iter().rev() {
+            input.bytes.push_front(*byte as u8);
+        }
+
+        Ok(())
+    }
+
+    fn interrupt_requested(&self) -> bool {
+        !self.input.lock().bytes.is_empty()
+    }
+
+    fn finish(&mut self) {
+        self.flush();
+
+        if let Some(error) = &self.output_error {
+            eprintln!("Could not write console output: {}", error);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+use super::Device;
+
+// The number of words in a sector
+pub const SECTOR_WORDS: u16 = 256;
+
+// Registers following the transfer buffer
+pub const SECTOR: u16 = SECTOR_WORDS;
+pub const COMMAND: u16 = SECTOR + 1;
+pub const STATUS: u16 = SECTOR + 2;
+pub const SECTOR_COUNT: u16 = SECTOR + 3;
+
+// The number of words the disk occupies
+pub const SIZE: u16 = SECTOR_COUNT + 1;
+
+// Commands
+pub const COMMAND_READ: u16 = 1;
+pub const COMMAND_WRITE: u16 = 2;
+
+// Status register bits
+pub const STATUS_ERROR: u16 = 1 << 0;
+
+const SECTOR_BYTES: u64 = SECTOR_WORDS as u64 * 2;
+
+/**
+ * A block storage device backed by an image file, which is a sequence of 512 byte sectors holding big endian words.
+ *
+ * Programs select a sector, then either read it into the transfer buffer or write the transfer buffer to it by
+ * writing to the command register. Transfers complete immediately, with the error bit of the status register set if
+ * the last command failed.
+ */
+pub struct Disk {
+    file: File,
+    buffer: Vec<u16>,
+    sector: u16,
+    sector_count: u16,
+    error: bool,
+}
+
+impl Disk {
+    pub fn open(path: &str) -> Result<Disk, String> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|e| format!("Could not open disk image {}: {}", path, e))?;
+
+        let length = file
+            .metadata()
+            .map_err(|e| format!("Could not read disk image {}: {}", path, e))?
+            .len();
+
+        // A trailing partial sector is treated as a full sector padded with zeros
+        let sector_count = length.div_ceil(SECTOR_BYTES);
+
+        if sector_count > u16::MAX as u64 {
+            return Err(format!("Disk image {} has more than 65535 sectors", path));
+        }
+
+        Ok(Disk {
+            file,
+            buffer: vec![0; SECTOR_WORDS as usize],
+            sector: 0,
+            sector_count: sector_count as u16,
+            error: false,
+        })
+    }
+
+    fn read_sector(&mut self) -> Result<(), String> {
+        let mut bytes = Vec::new();
+
+        self.file
+            .seek(SeekFrom::Start(self.sector as u64 * SECTOR_BYTES))
+            .and_then(|_| (&mut self.file).take(SECTOR_BYTES).read_to_end(&mut bytes))
+            .map_err(|e| e.to_string())?;
+
+        bytes.resize(SECTOR_BYTES as usize, 0);
+
+        for (word, chunk) in self.buffer.iter_mut().zip(bytes.chunks(2)) {
+            *word = u16::from_be_bytes([chunk[0], chunk[1]]);
+        }
+
+        Ok(())
+    }
+
+    fn write_sector(&mut self) -> Result<(), String> {
+        let bytes: Vec<u8> = self.buffer.iter().flat_map(|word| word.to_be_bytes()).collect();
+
+        self.file
+            .seek(SeekFrom::Start(self.sector as u64 * SECTOR_BYTES))
+            .and_then(|_| self.file.write_all(&bytes))
+            .map_err(|e| e.to_string())
+    }
+
+    fn execute(&mut self, command: u16) -> Result<(), String> {
+        if self.sector >= self.sector_count {
+            return Err(format!("Sector {} is outside of the disk", self.sector));
+        }
+
+        match command {
+            COMMAND_READ => self.read_sector(),
+            COMMAND_WRITE => self.write_sector(),
+            _ => Err(format!("Unrecognized disk command {}", command)),
+        }
+    }
+}
+
+impl Device for Disk {
+    fn name(&self) -> &str {
+        "disk"
+    }
+
+    fn load(&mut self, offset: u16) -> u16 {
+        match offset {
+            SECTOR => self.sector,
+            STATUS => self.error as u16 * STATUS_ERROR,
+            SECTOR_COUNT => self.sector_count,
+            COMMAND => 0,
+            _ => *self.buffer.get(offset as usize).unwrap_or(&0),
+        }
+    }
+
+    fn store(&mut self, offset: u16, value: u16) {
+        match offset {
+            SECTOR => self.sector = value,
+            COMMAND => self.error = self.execute(value).is_err(),
+            _ if offset < SECTOR_WORDS => self.buffer[offset as usize] = value,
+            _ => {}
+        }
+    }
+
+    /**
+     * The transfer buffer followed by the selected sector and the error bit. The image file isn't included.
+     */
+    fn save(&self) -> Vec<u16> {
+        self.buffer
+            .iter()
+            .copied()
+            .chain([self.sector, self.error as u16])
+            .collect()
+    }
+
+    fn restore(&mut self, data: &[u16]) -> Result<(), String> {
+        let (buffer, [sector, error]) = data.split_at(data.len().saturating_sub(2)) else {
+            return Err("Invalid disk state".to_string());
+        };
+
+        if buffer.len() != SECTOR_WORDS as usize {
+            return Err("Invalid disk state".to_string());
+        }
+
+        self.buffer.copy_from_slice(buffer);
+        self.sector = *sector;
+        self.error = *error != 0;
+
+        Ok(())
+    }
+
+    fn finish(&mut self) {
+        self.file.flush().expect("Could not write disk image");
+    }
+}
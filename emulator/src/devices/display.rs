@@ -0,0 +1,245 @@
+use std::{
+    fs,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use crate::clock::Clock;
+
+use super::Device;
+
+pub const COLUMNS: u16 = 80;
+pub const ROWS: u16 = 25;
+
+// Registers following the character buffer
+pub const CURSOR_X: u16 = COLUMNS * ROWS;
+pub const CURSOR_Y: u16 = CURSOR_X + 1;
+pub const CONTROL: u16 = CURSOR_X + 2;
+
+// The number of words the display occupies
+pub const SIZE: u16 = CONTROL + 1;
+
+// Control register bits
+pub const CONTROL_SHOW_CURSOR: u16 = 1 << 0;
+
+// The minimum time between redraws, so programs updating the buffer in a loop don't flood the terminal
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+// Checking the time is comparatively slow, so it is only done every this many instructions
+const TICKS_PER_FRAME_CHECK: u32 = 1024;
+
+enum Output {
+    // Draw the display on the terminal using ANSI escape codes
+    Terminal,
+    // Write the characters on the display to a file
+    File(String),
+}
+
+/**
+ * An 80x25 text mode display. Each cell of the buffer holds a character in its low byte and an attribute in its high
+ * byte, with the foreground colour in the low nibble of the attribute and the background colour in the high nibble.
+ * Colours are the 16 ANSI colours, an attribute of 0 uses the terminal's default colours.
+ */
+pub struct Display {
+    cells: Vec<u16>,
+    cursor_x: u16,
+    cursor_y: u16,
+    control: u16,
+    output: Output,
+    // Whether the display has changed since it was last drawn
+    dirty: bool,
+    ticks: u32,
+    last_frame: Option<Instant>,
+}
+
+impl Display {
+    pub fn terminal() -> Display {
+        Display::new(Output::Terminal)
+    }
+
+    pub fn file(path: &str) -> Display {
+        Display::new(Output::File(path.to_owned()))
+    }
+
+    fn new(output: Output) -> Display {
+        Display {
+            cells: vec![0; (COLUMNS * ROWS) as usize],
+            cursor_x: 0,
+            cursor_y: 0,
+            control: 0,
+            output,
+            dirty: true,
+            ticks: 0,
+            last_frame: None,
+        }
+    }
+
+    /**
+     * The characters on the display, one line per row with trailing spaces removed
+     */
+    pub fn text(&self) -> String {
+        self.cells
+            .chunks(COLUMNS as usize)
+            .map(|row| {
+                let line: String = row.iter().map(|cell| printable(*cell)).collect();
+
+                line.trim_end().to_owned() + "\n"
+            })
+            .collect()
+    }
+
+    fn load_register(&self, offset: u16) -> u16 {
+        match offset {
+            CURSOR_X => self.cursor_x,
+            CURSOR_Y => self.cursor_y,
+            CONTROL => self.control,
+            _ => *self.cells.get(offset as usize).unwrap_or(&0),
+        }
+    }
+
+    fn draw(&mut self) {
+        match &self.output {
+            Output::Terminal => {
+                let mut frame = String::new();
+
+                // Clear the screen before the first frame, afterwards everything is overwritten in place
+                if self.last_frame.is_none() {
+                    frame += "\x1b[2J";
+                }
+
+                frame += "\x1b[?25l\x1b[H";
+
+                for (index, row) in self.cells.chunks(COLUMNS as usize).enumerate() {
+                    let mut attribute = None;
+
+                    frame += &format!("\x1b[{};1H", index + 1);
+
+                    for cell in row {
+                        if attribute != Some(cell >> 8) {
+                            attribute = Some(cell >> 8);
+                            frame += &sgr(cell >> 8);
+                        }
+
+                        frame.push(printable(*cell));
+                    }
+
+                    frame += "\x1b[0m";
+                }
+
+                frame += &format!(
+                    "\x1b[{};{}H",
+                    self.cursor_y.min(ROWS - 1) + 1,
+                    self.cursor_x.min(COLUMNS - 1) + 1
+                );
+
+                if self.control & CONTROL_SHOW_CURSOR != 0 {
+                    frame += "\x1b[?25h";
+                }
+
+                let mut stdout = io::stdout();
+
+                stdout.write_all(frame.as_bytes()).unwrap();
+                stdout.flush().unwrap();
+            }
+            Output::File(path) => fs::write(path, self.text()).expect("Could not write display"),
+        }
+
+        self.dirty = false;
+        self.last_frame = Some(Instant::now());
+    }
+}
+
+impl Device for Display {
+    fn name(&self) -> &str {
+        "display"
+    }
+
+    fn load(&mut self, offset: u16) -> u16 {
+        self.load_register(offset)
+    }
+
+    fn store(&mut self, offset: u16, value: u16) {
+        match offset {
+            CURSOR_X => self.cursor_x = value,
+            CURSOR_Y => self.cursor_y = value,
+            CONTROL => self.control = value,
+            _ if offset < CURSOR_X => self.cells[offset as usize] = value,
+            _ => return,
+        }
+
+        self.dirty = true;
+    }
+
+    /**
+     * The buffer followed by the cursor and control registers, the same layout the display is mapped with
+     */
+    fn save(&self) -> Vec<u16> {
+        (0..SIZE).map(|offset| self.load_register(offset)).collect()
+    }
+
+    fn restore(&mut self, data: &[u16]) -> Result<(), String> {
+        if data.len() != SIZE as usize {
+            return Err("Invalid display state".to_string());
+        }
+
+        for (offset, value) in data.iter().enumerate() {
+            self.store(offset as u16, *value);
+        }
+
+        Ok(())
+    }
+
+    fn tick(&mut self, _: &Clock) {
+        self.ticks += 1;
+
+        if !self.dirty || self.ticks < TICKS_PER_FRAME_CHECK {
+            return;
+        }
+
+        self.ticks = 0;
+
+        if self
+            .last_frame
+            .is_none_or(|last_frame| last_frame.elapsed() >= FRAME_INTERVAL)
+        {
+            self.draw();
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.dirty || self.last_frame.is_none() {
+            self.draw();
+        }
+
+        // Leave the terminal's cursor below the display
+        if let Output::Terminal = self.output {
+            print!("\x1b[0m\x1b[?25h\x1b[{};1H", ROWS + 1);
+            io::stdout().flush().unwrap();
+        }
+    }
+}
+
+// The character in a cell, replacing control characters and anything outside of ASCII with a space
+fn printable(cell: u16) -> char {
+    match (cell & 0xFF) as u8 {
+        character @ 0x20..=0x7E => character as char,
+        _ => ' ',
+    }
+}
+
+// The ANSI escape sequence selecting the colours of an attribute
+fn sgr(attribute: u16) -> String {
+    if attribute == 0 {
+        return "\x1b[0m".to_string();
+    }
+
+    let colour_code = |colour: u16, base: u16| match colour & 0x8 {
+        0 => base + (colour & 0x7),
+        _ => base + 60 + (colour & 0x7),
+    };
+
+    format!(
+        "\x1b[0;{};{}m",
+        colour_code(attribute & 0xF, 30),
+        colour_code((attribute >> 4) & 0xF, 40)
+    )
+}
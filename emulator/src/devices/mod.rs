@@ -0,0 +1,248 @@
+pub mod console;
+pub mod disk;
+pub mod display;
+pub mod timer;
+
+pub use console::{Console, Input, InputQueue};
+pub use disk::Disk;
+pub use display::Display;
+pub use timer::Timer;
+
+use std::ops::RangeInclusive;
+
+use crate::{clock::Clock, debugger::parse_number, interrupts::INTERRUPT_LINES};
+
+// Where the console and timer, which are always mapped, are found
+pub const CONSOLE_ADDRESS: u16 = 0xFFFC;
+pub const TIMER_ADDRESS: u16 = 0xFFE0;
+pub const TIMER_INTERRUPT_LINE: u8 = 0;
+pub const CONSOLE_INTERRUPT_LINE: u8 = 1;
+
+/**
+ * A peripheral mapped into a range of the address space. Loads and stores to the range are forwarded to the device
+ * instead of memory, with `offset` being relative to the start of the range.
+ */
+pub trait Device: Send {
+    // The kind of device, used when listing mapped devices
+    fn name(&self) -> &str;
+
+    fn load(&mut self, offset: u16) -> u16;
+
+    fn store(&mut self, offset: u16, value: u16);
+
+    /**
+     * Called after every executed instruction with the emulator's clock
+     */
+    fn tick(&mut self, _clock: &Clock) {}
+
+    /**
+     * Whether the device is asserting its interrupt request line
+     */
+    fn interrupt_requested(&self) -> bool {
+        false
+    }
+
+    /**
+     * Called once the program has stopped running, e.g. to flush buffered output
+     */
+    fn finish(&mut self) {}
+
+    /**
+     * The device's state, for snapshots
+     */
+    fn save(&self) -> Vec<u16> {
+        Vec::new()
+    }
+
+    /**
+     * Restore state returned by `save`
+     */
+    fn restore(&mut self, _data: &[u16]) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/**
+ * Map a console and a timer at their standard addresses
+ */
+pub fn map_standard_devices(bus: &mut Bus, console: Console) {
+    bus.map(
+        CONSOLE_ADDRESS..=CONSOLE_ADDRESS + (console::SIZE - 1),
+        Box::new(console),
+        Some(CONSOLE_INTERRUPT_LINE),
+    );
+    bus.map(
+        TIMER_ADDRESS..=TIMER_ADDRESS + (timer::SIZE - 1),
+        Box::new(Timer::new()),
+        Some(TIMER_INTERRUPT_LINE),
+    );
+}
+
+pub struct Mapping {
+    pub range: RangeInclusive<u16>,
+    pub device: Box<dyn Device>,
+    // The interrupt request line the device is connected to
+    pub interrupt_line: Option<u8>,
+}
+
+/**
+ * Decodes addresses to the devices mapped at them. When mappings overlap, the most recently mapped device wins.
+ */
+#[derive(Default)]
+pub struct Bus {
+    mappings: Vec<Mapping>,
+}
+
+impl Bus {
+    pub fn new() -> Bus {
+        Bus::default()
+    }
+
+    pub fn map(&mut self, range: RangeInclusive<u16>, device: Box<dyn Device>, interrupt_line: Option<u8>) {
+        self.mappings.push(Mapping {
+            range,
+            device,
+            interrupt_line,
+        });
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    pub fn mappings_mut(&mut self) -> &mut [Mapping] {
+        &mut self.mappings
+    }
+
+    /**
+     * The device mapped at an address and the offset of the address within the device's range
+     */
+    pub fn device_at(&mut self, address: u16) -> Option<(&mut (dyn Device + 'static), u16)> {
+        self.mappings
+            .iter_mut()
+            .rev()
+            .find(|mapping| mapping.range.contains(&address))
+            .map(|mapping| (mapping.device.as_mut(), address - mapping.range.start()))
+    }
+
+    pub fn tick(&mut self, clock: &Clock) {
+        for mapping in self.mappings.iter_mut() {
+            mapping.device.tick(clock);
+        }
+    }
+
+    pub fn finish(&mut self) {
+        for mapping in self.mappings.iter_mut() {
+            mapping.device.finish();
+        }
+    }
+
+    /**
+     * A bit set for each interrupt request line asserted by a device
+     */
+    pub fn interrupt_lines(&self) -> u16 {
+        self.mappings
+            .iter()
+            .filter(|mapping| mapping.device.interrupt_requested())
+            .filter_map(|mapping| mapping.interrupt_line)
+            .fold(0, |lines, line| lines | 1 << line)
+    }
+}
+
+type DeviceConstructor = fn(Option<&str>) -> Result<Box<dyn Device>, String>;
+
+struct DeviceKind {
+    name: &'static str,
+    // The number of words mapped when no end address is given
+    size: u16,
+    create: DeviceConstructor,
+}
+
+const DEVICE_KINDS: &[DeviceKind] = &[
+    DeviceKind {
+        name: "console",
+        size: console::SIZE,
+        create: |path| match path {
+            Some(path) => Ok(Box::new(Console::host().with_output_file(path)?)),
+            None => Ok(Box::new(Console::host())),
+        },
+    },
+    DeviceKind {
+        name: "timer",
+        size: timer::SIZE,
+        create: |_| Ok(Box::new(Timer::new())),
+    },
+    DeviceKind {
+        name: "display",
+        size: display::SIZE,
+        create: |path| match path {
+            Some(path) => Ok(Box::new(Display::file(path))),
+            None => Ok(Box::new(Display::terminal())),
+        },
+    },
+    DeviceKind {
+        name: "disk",
+        size: disk::SIZE,
+        create: |path| Ok(Box::new(Disk::open(path.ok_or("A disk requires an image path")?)?)),
+    },
+];
+
+/**
+ * Create a device from a specification of the form `KIND@START[..END][/IRQ][:ARGS]`, e.g. `console@0xFFF0:out.txt` or
+ * `timer@0xFFC0/2`
+ */
+pub fn from_specification(specification: &str) -> Result<Mapping, String> {
+    let (name, rest) = specification
+        .split_once('@')
+        .ok_or(format!("Invalid device \"{}\", expected KIND@ADDRESS", specification))?;
+
+    let kind = DEVICE_KINDS.iter().find(|kind| kind.name == name).ok_or(format!(
+        "Unrecognized device kind \"{}\", expected one of: {}",
+        name,
+        DEVICE_KINDS
+            .iter()
+            .map(|kind| kind.name)
+            .collect::<Vec<&str>>()
+            .join(", ")
+    ))?;
+
+    let (range, args) = match rest.split_once(':') {
+        Some((range, args)) => (range, Some(args)),
+        None => (rest, None),
+    };
+
+    let (range, interrupt_line) = match range.split_once('/') {
+        Some((range, line)) => match line.parse::<u8>() {
+            Ok(line) if line < INTERRUPT_LINES => (range, Some(line)),
+            _ => return Err(format!("Invalid interrupt line \"{}\"", line)),
+        },
+        None => (range, None),
+    };
+
+    let range = match range.split_once("..") {
+        Some((start, end)) => {
+            let start = parse_number(start)?;
+            let end = parse_number(end)?;
+
+            if end <= start {
+                return Err(format!("Invalid range {}, the end must be after the start", range));
+            }
+
+            start..=(end - 1)
+        }
+        None => {
+            let start = parse_number(range)?;
+            let end = start
+                .checked_add(kind.size - 1)
+                .ok_or(format!("A {} device does not fit at {:04X}", name, start))?;
+
+            start..=end
+        }
+    };
+
+    Ok(Mapping {
+        range,
+        device: (kind.create)(args)?,
+        interrupt_line,
+    })
+}
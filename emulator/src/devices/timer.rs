@@ -0,0 +1,201 @@
+use crate::clock::Clock;
+
+use super::Device;
+
+pub const CYCLES_LOW: u16 = 0;
+pub const CYCLES_HIGH: u16 = 1;
+pub const MILLISECONDS_LOW: u16 = 2;
+pub const MILLISECONDS_HIGH: u16 = 3;
+pub const COUNTDOWN: u16 = 4;
+pub const CONTROL: u16 = 5;
+pub const STATUS: u16 = 6;
+
+// The number of words the timer occupies
+pub const SIZE: u16 = STATUS + 1;
+
+// Control register bits
+pub const CONTROL_ENABLE: u16 = 1 << 0;
+// Reload the countdown with its initial value when it expires instead of stopping
+pub const CONTROL_PERIODIC: u16 = 1 << 1;
+pub const CONTROL_INTERRUPT: u16 = 1 << 2;
+// Count down in milliseconds instead of cycles
+pub const CONTROL_MILLISECONDS: u16 = 1 << 3;
+
+// Status register bits
+pub const STATUS_EXPIRED: u16 = 1 << 0;
+
+/**
+ * A free running cycle counter and millisecond clock, and a programmable countdown which can raise an interrupt. Both
+ * follow the emulator's clock, so they use virtual time unless the emulator is running in realtime.
+ *
+ * The 32 bit counters are read low word first, which latches the high word so the two halves are consistent.
+ */
+pub struct Timer {
+    // The clock as of the last tick
+    clock: Clock,
+    latched_high: u16,
+    control: u16,
+    expired: bool,
+    // The value written to the countdown register, used to reload a periodic countdown
+    reload: u16,
+    remaining: u16,
+    // The clock reading when the countdown last decremented, in milliseconds mode
+    last_millisecond: u64,
+}
+
+impl Timer {
+    pub fn new() -> Timer {
+        Timer {
+            clock: Clock::default(),
+            latched_high: 0,
+            control: 0,
+            expired: false,
+            reload: 0,
+            remaining: 0,
+            last_millisecond: 0,
+        }
+    }
+
+    fn count_down(&mut self, amount: u64) {
+        if amount < self.remaining as u64 {
+            self.remaining -= amount as u16;
+            return;
+        }
+
+        self.expired = true;
+
+        match self.control & CONTROL_PERIODIC != 0 && self.reload != 0 {
+            true => self.remaining = self.reload,
+            false => {
+                self.remaining = 0;
+                self.control &= !CONTROL_ENABLE;
+            }
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Timer {
+        Timer::new()
+    }
+}
+
+impl Device for Timer {
+    fn name(&self) -> &str {
+        "timer"
+    }
+
+    fn load(&mut self, offset: u16) -> u16 {
+        match offset {
+            CYCLES_LOW => {
+                let cycles = self.clock.cycles();
+
+                self.latched_high = (cycles >> 16) as u16;
+                cycles as u16
+            }
+            MILLISECONDS_LOW => {
+                let milliseconds = self.clock.milliseconds();
+
+                self.latched_high = (milliseconds >> 16) as u16;
+                milliseconds as u16
+            }
+            CYCLES_HIGH | MILLISECONDS_HIGH => self.latched_high,
+            COUNTDOWN => self.remaining,
+            CONTROL => self.control,
+            STATUS => self.expired as u16 * STATUS_EXPIRED,
+            _ => 0,
+        }
+    }
+
+    fn store(&mut self, offset: u16, value: u16) {
+        match offset {
+            COUNTDOWN => {
+                self.reload = value;
+                self.remaining = value;
+                self.last_millisecond = self.clock.milliseconds();
+            }
+            CONTROL => {
+                // Restart the millisecond countdown from now when it is enabled
+                if value & CONTROL_ENABLE != 0 && self.control & CONTROL_ENABLE == 0 {
+                    self.last_millisecond = self.clock.milliseconds();
+                }
+
+                self.control = value;
+            }
+            // Writing the expired bit acknowledges the expiry
+            STATUS if value & STATUS_EXPIRED != 0 => self.expired = false,
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, clock: &Clock) {
+        // The machine's clock can be behind the timer's, e.g. after loading a snapshot which doesn't include this timer
+        let elapsed_cycles = clock.cycles().saturating_sub(self.clock.cycles());
+
+        self.clock = *clock;
+
+        if self.control & CONTROL_ENABLE == 0 {
+            return;
+        }
+
+        match self.control & CONTROL_MILLISECONDS != 0 {
+            true => {
+                let now = self.clock.milliseconds();
+                let elapsed = now.saturating_sub(self.last_millisecond);
+
+                if elapsed > 0 {
+                    self.last_millisecond = now;
+                    self.count_down(elapsed);
+                }
+            }
+            false => self.count_down(elapsed_cycles),
+        }
+    }
+
+    fn save(&self) -> Vec<u16> {
+        [
+            self.control,
+            self.expired as u16,
+            self.reload,
+            self.remaining,
+            self.latched_high,
+        ]
+        .into_iter()
+        .chain(
+            [self.last_millisecond, self.clock.cycles(), self.clock.virtual_cycles()]
+                .into_iter()
+                .flat_map(|value| (0..4).rev().map(move |index| (value >> (index * 16)) as u16)),
+        )
+        .collect()
+    }
+
+    fn restore(&mut self, data: &[u16]) -> Result<(), String> {
+        let [control, expired, reload, remaining, latched_high, times @ ..] = data else {
+            return Err("Invalid timer state".to_string());
+        };
+
+        let [last_millisecond, cycles, virtual_cycles] = match times.len() {
+            12 => [0, 1, 2].map(|index| {
+                times[index * 4..index * 4 + 4]
+                    .iter()
+                    .fold(0, |value, word| (value << 16) | *word as u64)
+            }),
+            _ => return Err("Invalid timer state".to_string()),
+        };
+
+        self.control = *control;
+        self.expired = *expired != 0;
+        self.reload = *reload;
+        self.remaining = *remaining;
+        self.latched_high = *latched_high;
+        self.last_millisecond = last_millisecond;
+        // The countdown continues from the time the snapshot was taken
+        self.clock.set_time(cycles, virtual_cycles);
+
+        Ok(())
+    }
+
+    fn interrupt_requested(&self) -> bool {
+        self.expired && self.control & CONTROL_INTERRUPT != 0
+    }
+}
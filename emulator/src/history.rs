@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use shared::BranchConditions;
+
+use crate::{interrupts::InterruptController, state::State};
+
+/**
+ * The state required to undo a single executed instruction
+ */
+struct StepRecord {
+    pc: u16,
+    registers: [u16; 8],
+    flags: BranchConditions,
+    halt: bool,
+    call_stack_pointer: u16,
+    // The call stack entry at `call_stack_pointer`, which is the only entry an instruction can overwrite
+    call_stack_entry: u16,
+    interrupts: InterruptController,
+    // (address, previous value) for each memory write, in the order they were made
+    memory_writes: Vec<(u16, u16)>,
+}
+
+/**
+ * An undo log of executed instructions, allowing execution to be stepped backwards.
+ *
+ * Registers, flags, the PC, the call stack and memory are restored when stepping backwards, however I/O side effects
+ * (e.g. characters written to stdout or read from stdin) are not undone.
+ */
+pub struct History {
+    records: VecDeque<StepRecord>,
+    // The maximum number of records kept, the oldest are discarded first
+    limit: usize,
+    // The number of instructions executed since history was enabled, minus the number undone
+    position: u64,
+    checkpoints: Vec<(usize, u64)>,
+    next_checkpoint_id: usize,
+}
+
+impl History {
+    pub fn new(limit: usize) -> History {
+        History {
+            records: VecDeque::new(),
+            limit,
+            position: 0,
+            checkpoints: Vec::new(),
+            next_checkpoint_id: 1,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /**
+     * Record the state before an instruction is executed
+     */
+    pub fn begin_step(state: &mut State) {
+        if state.history.is_none() {
+            return;
+        }
+
+        let record = StepRecord {
+            pc: state.pc,
+            registers: state.registers,
+            flags: state.flags,
+            halt: state.halt,
+            call_stack_pointer: state.call_stack_pointer,
+            // Nothing is above a full call stack, so there's no entry to record
+            call_stack_entry: state
+                .call_stack
+                .get(state.call_stack_pointer as usize)
+                .copied()
+                .unwrap_or(0),
+            interrupts: state.interrupts,
+            memory_writes: Vec::new(),
+        };
+
+        let history = state.history.as_mut().unwrap();
+
+        if history.records.len() == history.limit {
+            history.records.pop_front();
+        }
+
+        history.records.push_back(record);
+        history.position += 1;
+    }
+
+    pub fn record_memory_write(&mut self, address: u16, previous_value: u16) {
+        if let Some(record) = self.records.back_mut() {
+            record.memory_writes.push((address, previous_value));
+        }
+    }
+
+    /**
+     * Undo the most recently executed instruction, returning false if there is no history left to undo
+     */
+    pub fn undo(state: &mut State) -> bool {
+        let record = match state.history.as_mut().and_then(|history| history.records.pop_back()) {
+            Some(record) => record,
+            None => return false,
+        };
+
+        for (address, previous_value) in record.memory_writes.iter().rev() {
+            state.memory[*address as usize] = *previous_value;
+            state.invalidate_decoded(*address);
+        }
+
+        // Faults and exiting halt the program, so the undone instruction is the only one which could have done either
+        state.fault = None;
+        state.exit_status = None;
+        state.pc = record.pc;
+        state.registers = record.registers;
+        state.flags = record.flags;
+        state.halt = record.halt;
+        state.call_stack_pointer = record.call_stack_pointer;
+        if let Some(entry) = state.call_stack.get_mut(record.call_stack_pointer as usize) {
+            *entry = record.call_stack_entry;
+        }
+        state.interrupts = record.interrupts;
+
+        state.history.as_mut().unwrap().position -= 1;
+
+        true
+    }
+
+    /**
+     * Whether the most recently executed instruction wrote to the given address
+     */
+    pub fn last_step_wrote(&self, address: u16) -> bool {
+        self.last_step_writes().iter().any(|(written, _)| *written == address)
+    }
+
+    /**
+     * (address, previous value) for each memory write made by the most recently executed instruction
+     */
+    pub fn last_step_writes(&self) -> &[(u16, u16)] {
+        match self.records.back() {
+            Some(record) => &record.memory_writes,
+            None => &[],
+        }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    // The number of instructions which can currently be undone
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn add_checkpoint(&mut self) -> usize {
+        let id = self.next_checkpoint_id;
+
+        self.next_checkpoint_id += 1;
+        self.checkpoints.push((id, self.position));
+
+        id
+    }
+
+    pub fn checkpoint_position(&self, id: usize) -> Option<u64> {
+        self.checkpoints
+            .iter()
+            .find(|(checkpoint_id, _)| *checkpoint_id == id)
+            .map(|(_, position)| *position)
+    }
+
+    pub fn checkpoints(&self) -> &[(usize, u64)] {
+        &self.checkpoints
+    }
+}
@@ -1,15 +1,20 @@
 use std::fmt::{Debug, Formatter, Result as FormatResult};
 
+use shared::BranchConditions;
+
 use crate::state::State;
 
 use super::{ArtihmeticMode, Imm5, Instruction, Register};
 
+#[derive(Clone, Copy)]
 pub struct Add {
     pub dr: Register,
     pub sr0: Register,
     pub mode: ArtihmeticMode,
     pub sr1: Register,
     pub imm5: Imm5,
+    // ADC, which also adds the carry flag
+    pub with_carry: bool,
 }
 
 impl Instruction for Add {
@@ -23,6 +28,7 @@ impl Instruction for Add {
         };
         let sr1 = ((machine_code >> 2) & 0b111) as Register;
         let imm5 = (machine_code & 0b11111) as Imm5;
+        let with_carry = mode == ArtihmeticMode::Register && machine_code & 0b1 == 1;
 
         Add {
             dr,
@@ -30,6 +36,7 @@ impl Instruction for Add {
             mode,
             sr1,
             imm5,
+            with_carry,
         }
     }
 
@@ -40,13 +47,25 @@ impl Instruction for Add {
             ArtihmeticMode::Immediate => self.imm5 as u16,
         };
 
-        state.set_register_and_flags(self.dr, first_value.wrapping_add(second_value));
+        let carry_in = (self.with_carry && state.flags.contains(BranchConditions::CARRY)) as u16;
+
+        let (partial, first_carry) = first_value.overflowing_add(second_value);
+        let (result, second_carry) = partial.overflowing_add(carry_in);
+
+        // Overflow is when both operands have the same sign and the result's sign differs
+        let overflow = !(first_value ^ second_value) & (first_value ^ result) & 0x8000 != 0;
+
+        state.set_register_and_arithmetic_flags(self.dr, result, first_carry || second_carry, overflow);
     }
 }
 
 impl Debug for Add {
     fn fmt(&self, f: &mut Formatter) -> FormatResult {
-        write!(f, "ADD R{}, ", self.dr)?;
+        match self.with_carry {
+            true => write!(f, "ADC R{}, ", self.dr)?,
+            false => write!(f, "ADD R{}, ", self.dr)?,
+        }
+
         write!(f, "R{}, ", self.sr0)?;
 
         match self.mode {
@@ -6,6 +6,7 @@ use crate::{decode_signed_integer, state::State};
 
 use super::Instruction;
 
+#[derive(Clone, Copy)]
 pub struct Branch {
     pub conditions: BranchConditions,
     pub offset: i16,
@@ -14,6 +15,15 @@ pub struct Branch {
 impl Instruction for Branch {
     fn new(machine_code: u16) -> Branch {
         let conditions = BranchConditions::from_bits((machine_code >> 9) & 0b111).unwrap();
+
+        // Without any of n, z and p the branch is on c and v instead, which take two bits of the offset
+        if conditions.is_empty() {
+            let conditions = BranchConditions::from_bits(((machine_code >> 7) & 0b11) << 3).unwrap();
+            let offset = decode_signed_integer!(machine_code & 0b1111111, 7);
+
+            return Branch { conditions, offset };
+        }
+
         let encoded_offset = machine_code & 0b111111111;
 
         let offset = decode_signed_integer!(encoded_offset, 9);
@@ -22,7 +32,13 @@ impl Instruction for Branch {
     }
 
     fn execute(&self, state: &mut State) {
-        if state.flags & self.conditions != BranchConditions::empty() {
+        let taken = state.flags & self.conditions != BranchConditions::empty();
+
+        if let Some(coverage) = state.coverage.as_mut() {
+            coverage.record_branch(state.pc, taken);
+        }
+
+        if taken {
             state.pc = state.pc.wrapping_add_signed(self.offset);
         }
     }
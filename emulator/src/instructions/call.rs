@@ -1,9 +1,10 @@
 use std::fmt::{Debug, Formatter, Result as FormatResult};
 
-use crate::state::State;
+use crate::state::{State, CALL_STACK_SIZE};
 
-use super::Instruction;
+use super::{Instruction, IsaVersion};
 
+#[derive(Clone, Copy)]
 pub struct Call {
     subroutine_lookup_table_index: u16,
 }
@@ -16,13 +17,39 @@ impl Instruction for Call {
     }
 
     fn execute(&self, state: &mut State) {
-        let subroutine_address = state.memory[1 + self.subroutine_lookup_table_index as usize];
-
-        state.call_stack[state.call_stack_pointer as usize] = state.pc;
-        state.call_stack_pointer += 1;
+        if save_return_address(state) {
+            state.pc = state.memory[1 + self.subroutine_lookup_table_index as usize];
+        }
+    }
+}
 
-        state.pc = subroutine_address;
+/**
+ * Save the return address of the call instruction at the PC so RET can return after it, returning false if the call
+ * faulted
+ */
+pub fn save_return_address(state: &mut State) -> bool {
+    match state.isa_version {
+        IsaVersion::V1 => {
+            if state.call_stack_pointer as usize == CALL_STACK_SIZE {
+                state.raise_fault("Call stack overflow".to_string());
+                return false;
+            }
+
+            state.call_stack[state.call_stack_pointer as usize] = state.pc;
+            state.call_stack_pointer += 1;
+        }
+        IsaVersion::V2 => {
+            state.push(state.pc.wrapping_add(1));
+
+            // The hidden call stack is only a record for debuggers, so it stops growing once it's full
+            if (state.call_stack_pointer as usize) < CALL_STACK_SIZE {
+                state.call_stack[state.call_stack_pointer as usize] = state.pc;
+                state.call_stack_pointer += 1;
+            }
+        }
     }
+
+    true
 }
 
 impl Debug for Call {
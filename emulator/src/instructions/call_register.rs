@@ -0,0 +1,34 @@
+use std::fmt::{Debug, Formatter, Result as FormatResult};
+
+use crate::state::State;
+
+use super::{call::save_return_address, Instruction, Register};
+
+#[derive(Clone, Copy)]
+pub struct CallRegister {
+    base_register: Register,
+}
+
+impl Instruction for CallRegister {
+    fn new(machine_code: u16) -> CallRegister {
+        CallRegister {
+            base_register: (machine_code & 0b111) as Register,
+        }
+    }
+
+    fn execute(&self, state: &mut State) {
+        // Read before saving the return address, which moves the stack pointer from ISA version 2
+        let subroutine_address = state.registers[self.base_register as usize];
+
+        if save_return_address(state) {
+            // The PC is incremented after the instruction executes
+            state.pc = subroutine_address.wrapping_sub(1);
+        }
+    }
+}
+
+impl Debug for CallRegister {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        write!(f, "CALLR R{}", self.base_register)
+    }
+}
@@ -0,0 +1,24 @@
+use std::fmt::{Debug, Formatter, Result as FormatResult};
+
+use crate::state::State;
+
+use super::Instruction;
+
+#[derive(Clone, Copy)]
+pub struct DisableInterrupts {}
+
+impl Instruction for DisableInterrupts {
+    fn new(_machine_code: u16) -> DisableInterrupts {
+        DisableInterrupts {}
+    }
+
+    fn execute(&self, state: &mut State) {
+        state.interrupts.enabled = false;
+    }
+}
+
+impl Debug for DisableInterrupts {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        write!(f, "DI")
+    }
+}
@@ -0,0 +1,24 @@
+use std::fmt::{Debug, Formatter, Result as FormatResult};
+
+use crate::state::State;
+
+use super::Instruction;
+
+#[derive(Clone, Copy)]
+pub struct EnableInterrupts {}
+
+impl Instruction for EnableInterrupts {
+    fn new(_machine_code: u16) -> EnableInterrupts {
+        EnableInterrupts {}
+    }
+
+    fn execute(&self, state: &mut State) {
+        state.interrupts.enabled = true;
+    }
+}
+
+impl Debug for EnableInterrupts {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        write!(f, "EI")
+    }
+}
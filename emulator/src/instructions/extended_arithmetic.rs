@@ -0,0 +1,116 @@
+use std::fmt::{Debug, Formatter, Result as FormatResult};
+
+use crate::state::State;
+
+use super::{Instruction, Register};
+
+/**
+ * The operations in the extended opcode group, selected by the low three bits of the instruction
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExtendedOperation {
+    Multiply,
+    DivideUnsigned,
+    ModuloUnsigned,
+    DivideSigned,
+    ModuloSigned,
+    Xor,
+    Or,
+}
+
+impl ExtendedOperation {
+    pub fn from_bits(bits: u16) -> Option<ExtendedOperation> {
+        match bits {
+            0b000 => Some(ExtendedOperation::Multiply),
+            0b001 => Some(ExtendedOperation::DivideUnsigned),
+            0b010 => Some(ExtendedOperation::ModuloUnsigned),
+            0b011 => Some(ExtendedOperation::DivideSigned),
+            0b100 => Some(ExtendedOperation::ModuloSigned),
+            0b101 => Some(ExtendedOperation::Xor),
+            0b110 => Some(ExtendedOperation::Or),
+            _ => None,
+        }
+    }
+
+    fn mnemonic(self) -> &'static str {
+        match self {
+            ExtendedOperation::Multiply => "MUL",
+            ExtendedOperation::DivideUnsigned => "DIVU",
+            ExtendedOperation::ModuloUnsigned => "MODU",
+            ExtendedOperation::DivideSigned => "DIVS",
+            ExtendedOperation::ModuloSigned => "MODS",
+            ExtendedOperation::Xor => "XOR",
+            ExtendedOperation::Or => "OR",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ExtendedArithmetic {
+    pub operation: ExtendedOperation,
+    pub dr: Register,
+    pub sr0: Register,
+    pub sr1: Register,
+}
+
+impl Instruction for ExtendedArithmetic {
+    fn new(machine_code: u16) -> ExtendedArithmetic {
+        ExtendedArithmetic {
+            // Instructions with an operation which doesn't exist are decoded as invalid instead
+            operation: ExtendedOperation::from_bits(machine_code & 0b111).unwrap(),
+            dr: ((machine_code >> 9) & 0b111) as Register,
+            sr0: ((machine_code >> 6) & 0b111) as Register,
+            sr1: ((machine_code >> 3) & 0b111) as Register,
+        }
+    }
+
+    fn execute(&self, state: &mut State) {
+        let first_value = state.registers[self.sr0 as usize];
+        let second_value = state.registers[self.sr1 as usize];
+
+        let is_division = !matches!(
+            self.operation,
+            ExtendedOperation::Multiply | ExtendedOperation::Xor | ExtendedOperation::Or
+        );
+
+        if is_division && second_value == 0 {
+            state.raise_fault("Division by zero".to_string());
+            return;
+        }
+
+        // Signed division wraps, so -32768 / -1 is -32768
+        let result = match self.operation {
+            ExtendedOperation::Multiply => first_value.wrapping_mul(second_value),
+            ExtendedOperation::DivideUnsigned => first_value / second_value,
+            ExtendedOperation::ModuloUnsigned => first_value % second_value,
+            ExtendedOperation::DivideSigned => (first_value as i16).wrapping_div(second_value as i16) as u16,
+            ExtendedOperation::ModuloSigned => (first_value as i16).wrapping_rem(second_value as i16) as u16,
+            ExtendedOperation::Xor => first_value ^ second_value,
+            ExtendedOperation::Or => first_value | second_value,
+        };
+
+        if self.operation != ExtendedOperation::Multiply {
+            state.set_register_and_flags(self.dr, result);
+            return;
+        }
+
+        // Multiplying sets the carry and overflow flags if the product doesn't fit in 16 bits unsigned or signed
+        let carry = first_value.checked_mul(second_value).is_none();
+        let overflow = (first_value as i16).checked_mul(second_value as i16).is_none();
+
+        state.set_register_and_arithmetic_flags(self.dr, result, carry, overflow);
+    }
+}
+
+impl Debug for ExtendedArithmetic {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        write!(
+            f,
+            "{} R{}, R{}, R{}",
+            self.operation.mnemonic(),
+            self.dr,
+            self.sr0,
+            self.sr1
+        )
+    }
+}
@@ -4,6 +4,7 @@ use crate::state::State;
 
 use super::Instruction;
 
+#[derive(Clone, Copy)]
 pub struct Halt {}
 
 impl Instruction for Halt {
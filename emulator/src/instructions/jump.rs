@@ -0,0 +1,29 @@
+use std::fmt::{Debug, Formatter, Result as FormatResult};
+
+use crate::state::State;
+
+use super::{Instruction, Register};
+
+#[derive(Clone, Copy)]
+pub struct Jump {
+    base_register: Register,
+}
+
+impl Instruction for Jump {
+    fn new(machine_code: u16) -> Jump {
+        Jump {
+            base_register: (machine_code & 0b111) as Register,
+        }
+    }
+
+    fn execute(&self, state: &mut State) {
+        // The PC is incremented after the instruction executes
+        state.pc = state.registers[self.base_register as usize].wrapping_sub(1);
+    }
+}
+
+impl Debug for Jump {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        write!(f, "JMP R{}", self.base_register)
+    }
+}
@@ -0,0 +1,30 @@
+use std::fmt::{Debug, Formatter, Result as FormatResult};
+
+use crate::state::State;
+
+use super::Instruction;
+
+/**
+ * A jump to an absolute address held in the word after the instruction
+ */
+#[derive(Clone, Copy)]
+pub struct JumpLong {}
+
+impl Instruction for JumpLong {
+    fn new(_machine_code: u16) -> JumpLong {
+        JumpLong {}
+    }
+
+    fn execute(&self, state: &mut State) {
+        let address = state.memory[state.pc.wrapping_add(1) as usize];
+
+        // The PC is incremented after the instruction executes
+        state.pc = address.wrapping_sub(1);
+    }
+}
+
+impl Debug for JumpLong {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        write!(f, "JMPL")
+    }
+}
@@ -4,6 +4,7 @@ use crate::{decode_signed_integer, state::State};
 
 use super::{Instruction, Register};
 
+#[derive(Clone, Copy)]
 pub struct Load {
     destination_register: Register,
     base_register: Register,
@@ -27,13 +28,7 @@ impl Instruction for Load {
             false => base_register_value.wrapping_sub((0 - self.offset) as u16),
         };
 
-        let value = match address {
-            0xFFFE => match state.stdin.len() {
-                0 => 0x80 as u16,
-                _ => state.stdin.remove(0) as u16,
-            },
-            _ => state.memory[address as usize],
-        };
+        let value = state.read_memory(address);
 
         state.set_register_and_flags(self.destination_register, value);
     }
@@ -4,6 +4,7 @@ use crate::{decode_signed_integer, state::State};
 
 use super::{Instruction, Register};
 
+#[derive(Clone, Copy)]
 pub struct LoadEffectiveAddress {
     pub destination_register: Register,
     pub offset: i16,
@@ -4,6 +4,7 @@ use crate::state::State;
 
 use super::{Instruction, Register};
 
+#[derive(Clone, Copy)]
 pub struct LoadImmediate {
     pub dr: Register,
     pub immediate: u16,
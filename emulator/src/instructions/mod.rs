@@ -1,35 +1,97 @@
 mod add;
 mod branch;
 mod call;
+mod call_register;
+mod disable_interrupts;
+mod enable_interrupts;
+mod extended_arithmetic;
 mod halt;
+mod jump;
+mod jump_long;
 mod load;
 mod load_effective_address;
 mod load_immediate;
+mod pop;
+mod push;
 mod r#return;
+mod return_from_interrupt;
 mod sleep;
 mod store;
 mod sub;
 
-use std::fmt::Debug;
+use std::{
+    fmt::{Debug, Formatter, Result as FormatResult},
+    str::FromStr,
+};
 
 use add::Add;
 use branch::Branch;
 use call::Call;
+use call_register::CallRegister;
+use disable_interrupts::DisableInterrupts;
+use enable_interrupts::EnableInterrupts;
+use extended_arithmetic::{ExtendedArithmetic, ExtendedOperation};
 use halt::Halt;
+use jump::Jump;
+use jump_long::JumpLong;
 use load::Load;
 use load_effective_address::LoadEffectiveAddress;
 use load_immediate::LoadImmediate;
+use pop::Pop;
+use push::Push;
 use r#return::Return;
+use return_from_interrupt::ReturnFromInterrupt;
 use sleep::Sleep;
 use store::Store;
 use sub::Sub;
 
-use crate::state::State;
+use crate::{process::ARGUMENTS_ADDRESS, state::State};
 
 pub type Register = u8;
 pub type Imm5 = u8;
 
-#[derive(PartialEq, Eq, Debug)]
+// The register PUSH, POP and (from ISA version 2) CALL, CALLR and RET use as the stack pointer
+pub const STACK_POINTER: Register = 7;
+// Where the stack pointer starts in ISA version 2, just below the command line arguments, so calls made before the
+// program sets up its own stack don't overwrite the SLT or devices
+pub const INITIAL_STACK_POINTER: u16 = ARGUMENTS_ADDRESS - 1;
+
+/**
+ * The revision of the instruction set the machine implements
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum IsaVersion {
+    // Return addresses are kept on a hidden call stack, 256 calls deep
+    #[default]
+    V1 = 1,
+    // Return addresses are pushed to the stack in memory
+    V2 = 2,
+}
+
+impl TryFrom<u16> for IsaVersion {
+    type Error = String;
+
+    fn try_from(version: u16) -> Result<IsaVersion, String> {
+        match version {
+            1 => Ok(IsaVersion::V1),
+            2 => Ok(IsaVersion::V2),
+            _ => Err(format!("Unrecognized ISA version \"{}\", expected 1 or 2", version)),
+        }
+    }
+}
+
+impl FromStr for IsaVersion {
+    type Err = String;
+
+    fn from_str(version: &str) -> Result<IsaVersion, String> {
+        version
+            .parse::<u16>()
+            .map_err(|_| format!("Unrecognized ISA version \"{}\", expected 1 or 2", version))
+            .and_then(IsaVersion::try_from)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum ArtihmeticMode {
     Register,
     Immediate,
@@ -42,19 +104,183 @@ pub trait Instruction: Debug {
     fn execute(&self, state: &mut State);
 }
 
-pub fn from_machine_code(machine_code: u16) -> Box<dyn Instruction> {
-    match (machine_code >> 12) & 0xF {
-        0x0 => Box::new(Add::new(machine_code)),
-        0x1 => Box::new(Sub::new(machine_code)),
-        0x5 => Box::new(LoadEffectiveAddress::new(machine_code)),
-        0x6 => Box::new(Load::new(machine_code)),
-        0x7 => Box::new(LoadImmediate::new(machine_code)),
-        0x8 => Box::new(Store::new(machine_code)),
-        0x9 => Box::new(Branch::new(machine_code)),
-        0xA => Box::new(Call::new(machine_code)),
-        0xB => Box::new(Return::new(machine_code)),
-        0xC => Box::new(Halt::new(machine_code)),
-        0xD => Box::new(Sleep::new(machine_code)),
-        _ => panic!("Invalid opcode {:1X}", (machine_code >> 12) & 0xF),
+// The opcode of each instruction's mnemonic, the system instructions all share opcode E and the extended arithmetic
+// instructions all share opcode F
+pub const OPCODES: &[(&str, u8)] = &[
+    ("ADD", 0x0),
+    ("SUB", 0x1),
+    ("ADC", 0x0),
+    ("SBC", 0x1),
+    ("LEA", 0x5),
+    ("LD", 0x6),
+    ("LDI", 0x7),
+    ("ST", 0x8),
+    ("BR", 0x9),
+    ("CALL", 0xA),
+    ("RET", 0xB),
+    ("HLT", 0xC),
+    ("SLP", 0xD),
+    ("RTI", 0xE),
+    ("EI", 0xE),
+    ("DI", 0xE),
+    ("PUSH", 0xE),
+    ("POP", 0xE),
+    ("JMP", 0xE),
+    ("CALLR", 0xE),
+    ("JMPL", 0xE),
+    ("MUL", 0xF),
+    ("DIVU", 0xF),
+    ("MODU", 0xF),
+    ("DIVS", 0xF),
+    ("MODS", 0xF),
+    ("XOR", 0xF),
+    ("OR", 0xF),
+];
+
+/**
+ * A decoded instruction. These are small and cheap to copy, so they can be cached rather than decoded every time the
+ * instruction is executed.
+ */
+#[derive(Clone, Copy)]
+pub enum DecodedInstruction {
+    Add(Add),
+    Sub(Sub),
+    LoadEffectiveAddress(LoadEffectiveAddress),
+    Load(Load),
+    LoadImmediate(LoadImmediate),
+    Store(Store),
+    Branch(Branch),
+    Call(Call),
+    Return(Return),
+    Halt(Halt),
+    Sleep(Sleep),
+    ReturnFromInterrupt(ReturnFromInterrupt),
+    EnableInterrupts(EnableInterrupts),
+    DisableInterrupts(DisableInterrupts),
+    Push(Push),
+    Pop(Pop),
+    Jump(Jump),
+    CallRegister(CallRegister),
+    JumpLong(JumpLong),
+    ExtendedArithmetic(ExtendedArithmetic),
+    // A word which isn't a valid instruction, executing it faults
+    Invalid(u16),
+}
+
+impl DecodedInstruction {
+    pub fn decode(machine_code: u16) -> DecodedInstruction {
+        match (machine_code >> 12) & 0xF {
+            0x0 => DecodedInstruction::Add(Add::new(machine_code)),
+            0x1 => DecodedInstruction::Sub(Sub::new(machine_code)),
+            0x5 => DecodedInstruction::LoadEffectiveAddress(LoadEffectiveAddress::new(machine_code)),
+            0x6 => DecodedInstruction::Load(Load::new(machine_code)),
+            0x7 => DecodedInstruction::LoadImmediate(LoadImmediate::new(machine_code)),
+            0x8 => DecodedInstruction::Store(Store::new(machine_code)),
+            0x9 => DecodedInstruction::Branch(Branch::new(machine_code)),
+            0xA => DecodedInstruction::Call(Call::new(machine_code)),
+            0xB => DecodedInstruction::Return(Return::new(machine_code)),
+            0xC => DecodedInstruction::Halt(Halt::new(machine_code)),
+            0xD => DecodedInstruction::Sleep(Sleep::new(machine_code)),
+            0xE => match (machine_code >> 8) & 0xF {
+                0x0 => DecodedInstruction::ReturnFromInterrupt(ReturnFromInterrupt::new(machine_code)),
+                0x1 => DecodedInstruction::EnableInterrupts(EnableInterrupts::new(machine_code)),
+                0x2 => DecodedInstruction::DisableInterrupts(DisableInterrupts::new(machine_code)),
+                0x3 => DecodedInstruction::Push(Push::new(machine_code)),
+                0x4 => DecodedInstruction::Pop(Pop::new(machine_code)),
+                0x5 => DecodedInstruction::Jump(Jump::new(machine_code)),
+                0x6 => DecodedInstruction::CallRegister(CallRegister::new(machine_code)),
+                0x7 => DecodedInstruction::JumpLong(JumpLong::new(machine_code)),
+                _ => DecodedInstruction::Invalid(machine_code),
+            },
+            0xF => match ExtendedOperation::from_bits(machine_code & 0b111) {
+                Some(_) => DecodedInstruction::ExtendedArithmetic(ExtendedArithmetic::new(machine_code)),
+                None => DecodedInstruction::Invalid(machine_code),
+            },
+            _ => DecodedInstruction::Invalid(machine_code),
+        }
+    }
+
+    pub fn opcode(&self) -> u8 {
+        match self {
+            DecodedInstruction::Add(_) => 0x0,
+            DecodedInstruction::Sub(_) => 0x1,
+            DecodedInstruction::LoadEffectiveAddress(_) => 0x5,
+            DecodedInstruction::Load(_) => 0x6,
+            DecodedInstruction::LoadImmediate(_) => 0x7,
+            DecodedInstruction::Store(_) => 0x8,
+            DecodedInstruction::Branch(_) => 0x9,
+            DecodedInstruction::Call(_) => 0xA,
+            DecodedInstruction::Return(_) => 0xB,
+            DecodedInstruction::Halt(_) => 0xC,
+            DecodedInstruction::Sleep(_) => 0xD,
+            DecodedInstruction::ReturnFromInterrupt(_) => 0xE,
+            DecodedInstruction::EnableInterrupts(_) => 0xE,
+            DecodedInstruction::DisableInterrupts(_) => 0xE,
+            DecodedInstruction::Push(_) => 0xE,
+            DecodedInstruction::Pop(_) => 0xE,
+            DecodedInstruction::Jump(_) => 0xE,
+            DecodedInstruction::CallRegister(_) => 0xE,
+            DecodedInstruction::JumpLong(_) => 0xE,
+            DecodedInstruction::ExtendedArithmetic(_) => 0xF,
+            DecodedInstruction::Invalid(machine_code) => (machine_code >> 12) as u8,
+        }
+    }
+
+    pub fn execute(&self, state: &mut State) {
+        match self {
+            DecodedInstruction::Add(instruction) => instruction.execute(state),
+            DecodedInstruction::Sub(instruction) => instruction.execute(state),
+            DecodedInstruction::LoadEffectiveAddress(instruction) => instruction.execute(state),
+            DecodedInstruction::Load(instruction) => instruction.execute(state),
+            DecodedInstruction::LoadImmediate(instruction) => instruction.execute(state),
+            DecodedInstruction::Store(instruction) => instruction.execute(state),
+            DecodedInstruction::Branch(instruction) => instruction.execute(state),
+            DecodedInstruction::Call(instruction) => instruction.execute(state),
+            DecodedInstruction::Return(instruction) => instruction.execute(state),
+            DecodedInstruction::Halt(instruction) => instruction.execute(state),
+            DecodedInstruction::Sleep(instruction) => instruction.execute(state),
+            DecodedInstruction::ReturnFromInterrupt(instruction) => instruction.execute(state),
+            DecodedInstruction::EnableInterrupts(instruction) => instruction.execute(state),
+            DecodedInstruction::DisableInterrupts(instruction) => instruction.execute(state),
+            DecodedInstruction::Push(instruction) => instruction.execute(state),
+            DecodedInstruction::Pop(instruction) => instruction.execute(state),
+            DecodedInstruction::Jump(instruction) => instruction.execute(state),
+            DecodedInstruction::CallRegister(instruction) => instruction.execute(state),
+            DecodedInstruction::JumpLong(instruction) => instruction.execute(state),
+            DecodedInstruction::ExtendedArithmetic(instruction) => instruction.execute(state),
+            DecodedInstruction::Invalid(machine_code) => match (machine_code >> 12) & 0xF {
+                0xE => state.raise_fault(format!("Invalid system instruction {:04X}", machine_code)),
+                0xF => state.raise_fault(format!("Invalid extended instruction {:04X}", machine_code)),
+                opcode => state.raise_fault(format!("Invalid opcode {:1X}", opcode)),
+            },
+        }
+    }
+}
+
+impl Debug for DecodedInstruction {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        match self {
+            DecodedInstruction::Add(instruction) => instruction.fmt(f),
+            DecodedInstruction::Sub(instruction) => instruction.fmt(f),
+            DecodedInstruction::LoadEffectiveAddress(instruction) => instruction.fmt(f),
+            DecodedInstruction::Load(instruction) => instruction.fmt(f),
+            DecodedInstruction::LoadImmediate(instruction) => instruction.fmt(f),
+            DecodedInstruction::Store(instruction) => instruction.fmt(f),
+            DecodedInstruction::Branch(instruction) => instruction.fmt(f),
+            DecodedInstruction::Call(instruction) => instruction.fmt(f),
+            DecodedInstruction::Return(instruction) => instruction.fmt(f),
+            DecodedInstruction::Halt(instruction) => instruction.fmt(f),
+            DecodedInstruction::Sleep(instruction) => instruction.fmt(f),
+            DecodedInstruction::ReturnFromInterrupt(instruction) => instruction.fmt(f),
+            DecodedInstruction::EnableInterrupts(instruction) => instruction.fmt(f),
+            DecodedInstruction::DisableInterrupts(instruction) => instruction.fmt(f),
+            DecodedInstruction::Push(instruction) => instruction.fmt(f),
+            DecodedInstruction::Pop(instruction) => instruction.fmt(f),
+            DecodedInstruction::Jump(instruction) => instruction.fmt(f),
+            DecodedInstruction::CallRegister(instruction) => instruction.fmt(f),
+            DecodedInstruction::JumpLong(instruction) => instruction.fmt(f),
+            DecodedInstruction::ExtendedArithmetic(instruction) => instruction.fmt(f),
+            DecodedInstruction::Invalid(machine_code) => write!(f, "INVALID {:04X}", machine_code),
+        }
     }
 }
@@ -0,0 +1,30 @@
+use std::fmt::{Debug, Formatter, Result as FormatResult};
+
+use crate::state::State;
+
+use super::{Instruction, Register};
+
+#[derive(Clone, Copy)]
+pub struct Pop {
+    destination_register: Register,
+}
+
+impl Instruction for Pop {
+    fn new(machine_code: u16) -> Pop {
+        Pop {
+            destination_register: (machine_code & 0b111) as Register,
+        }
+    }
+
+    fn execute(&self, state: &mut State) {
+        let value = state.pop();
+
+        state.set_register_and_flags(self.destination_register, value);
+    }
+}
+
+impl Debug for Pop {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        write!(f, "POP R{}", self.destination_register)
+    }
+}
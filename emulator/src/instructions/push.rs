@@ -0,0 +1,28 @@
+use std::fmt::{Debug, Formatter, Result as FormatResult};
+
+use crate::state::State;
+
+use super::{Instruction, Register};
+
+#[derive(Clone, Copy)]
+pub struct Push {
+    source_register: Register,
+}
+
+impl Instruction for Push {
+    fn new(machine_code: u16) -> Push {
+        Push {
+            source_register: (machine_code & 0b111) as Register,
+        }
+    }
+
+    fn execute(&self, state: &mut State) {
+        state.push(state.registers[self.source_register as usize]);
+    }
+}
+
+impl Debug for Push {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        write!(f, "PUSH R{}", self.source_register)
+    }
+}
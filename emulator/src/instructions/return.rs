@@ -2,8 +2,9 @@ use std::fmt::{Debug, Formatter, Result as FormatResult};
 
 use crate::state::State;
 
-use super::Instruction;
+use super::{Instruction, IsaVersion};
 
+#[derive(Clone, Copy)]
 pub struct Return {}
 
 impl Instruction for Return {
@@ -12,8 +13,32 @@ impl Instruction for Return {
     }
 
     fn execute(&self, state: &mut State) {
-        state.call_stack_pointer -= 1;
-        state.pc = state.call_stack[state.call_stack_pointer as usize];
+        match state.isa_version {
+            IsaVersion::V1 => {
+                if state.call_stack_pointer == 0 {
+                    state.raise_fault("Returned with an empty call stack".to_string());
+                    return;
+                }
+
+                state.call_stack_pointer -= 1;
+                state.pc = state.call_stack[state.call_stack_pointer as usize];
+            }
+            IsaVersion::V2 => {
+                // The PC is incremented after the instruction executes
+                let return_address = state.pop();
+                let call_address = return_address.wrapping_sub(1);
+
+                // The program can rearrange the stack in memory (e.g. to switch coroutines), so the hidden record is
+                // only unwound when returning from its innermost call
+                if state.call_stack_pointer > 0
+                    && state.call_stack[state.call_stack_pointer as usize - 1] == call_address
+                {
+                    state.call_stack_pointer -= 1;
+                }
+
+                state.pc = call_address;
+            }
+        }
     }
 }
 
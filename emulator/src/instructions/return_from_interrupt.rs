@@ -0,0 +1,27 @@
+use std::fmt::{Debug, Formatter, Result as FormatResult};
+
+use crate::state::State;
+
+use super::Instruction;
+
+#[derive(Clone, Copy)]
+pub struct ReturnFromInterrupt {}
+
+impl Instruction for ReturnFromInterrupt {
+    fn new(_machine_code: u16) -> ReturnFromInterrupt {
+        ReturnFromInterrupt {}
+    }
+
+    fn execute(&self, state: &mut State) {
+        // The saved PC is the next instruction to execute, so account for the PC being incremented after this one
+        state.pc = state.interrupts.saved_pc.wrapping_sub(1);
+        state.flags = state.interrupts.saved_flags;
+        state.interrupts.enabled = true;
+    }
+}
+
+impl Debug for ReturnFromInterrupt {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        write!(f, "RTI")
+    }
+}
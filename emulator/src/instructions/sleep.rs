@@ -1,13 +1,10 @@
-use std::{
-    fmt::{Debug, Formatter, Result as FormatResult},
-    thread,
-    time::Duration,
-};
+use std::fmt::{Debug, Formatter, Result as FormatResult};
 
 use crate::state::State;
 
 use super::Instruction;
 
+#[derive(Clone, Copy)]
 pub struct Sleep {
     duration: u16,
 }
@@ -19,8 +16,8 @@ impl Instruction for Sleep {
         }
     }
 
-    fn execute(&self, _: &mut State) {
-        thread::sleep(Duration::from_millis(self.duration.into()));
+    fn execute(&self, state: &mut State) {
+        state.clock.sleep(self.duration);
     }
 }
 
@@ -1,12 +1,10 @@
-use std::{
-    fmt::{Debug, Formatter, Result as FormatResult},
-    io::{self, Write},
-};
+use std::fmt::{Debug, Formatter, Result as FormatResult};
 
 use crate::{decode_signed_integer, state::State};
 
 use super::{Instruction, Register};
 
+#[derive(Clone, Copy)]
 pub struct Store {
     base_register: Register,
     offset: i16,
@@ -30,16 +28,7 @@ impl Instruction for Store {
             false => base_register_value.wrapping_sub((0 - self.offset) as u16),
         };
 
-        match address {
-            0xFFFF => {
-                print!(
-                    "{}",
-                    String::from_utf8(vec![state.registers[self.source_register as usize] as u8]).unwrap()
-                );
-                io::stdout().flush().unwrap();
-            }
-            _ => state.memory[address as usize] = state.registers[self.source_register as usize],
-        }
+        state.write_memory(address, state.registers[self.source_register as usize]);
     }
 }
 
@@ -1,15 +1,20 @@
 use std::fmt::{Debug, Formatter, Result as FormatResult};
 
+use shared::BranchConditions;
+
 use crate::state::State;
 
 use super::{ArtihmeticMode, Imm5, Instruction, Register};
 
+#[derive(Clone, Copy)]
 pub struct Sub {
     pub dr: Register,
     pub sr0: Register,
     pub mode: ArtihmeticMode,
     pub sr1: Register,
     pub imm5: Imm5,
+    // SBC, which also subtracts the carry flag (the borrow from a previous subtraction)
+    pub with_carry: bool,
 }
 
 impl Instruction for Sub {
@@ -23,6 +28,7 @@ impl Instruction for Sub {
         };
         let sr1 = ((machine_code >> 2) & 0b111) as Register;
         let imm5 = (machine_code & 0b11111) as Imm5;
+        let with_carry = mode == ArtihmeticMode::Register && machine_code & 0b1 == 1;
 
         Sub {
             dr,
@@ -30,6 +36,7 @@ impl Instruction for Sub {
             mode,
             sr1,
             imm5,
+            with_carry,
         }
     }
 
@@ -40,13 +47,25 @@ impl Instruction for Sub {
             ArtihmeticMode::Immediate => self.imm5 as u16,
         };
 
-        state.set_register_and_flags(self.dr, first_value.wrapping_sub(second_value));
+        let carry_in = (self.with_carry && state.flags.contains(BranchConditions::CARRY)) as u16;
+
+        let (partial, first_carry) = first_value.overflowing_sub(second_value);
+        let (result, second_carry) = partial.overflowing_sub(carry_in);
+
+        // Overflow is when the operands have different signs and the result's sign differs from the first operand's
+        let overflow = (first_value ^ second_value) & (first_value ^ result) & 0x8000 != 0;
+
+        state.set_register_and_arithmetic_flags(self.dr, result, first_carry || second_carry, overflow);
     }
 }
 
 impl Debug for Sub {
     fn fmt(&self, f: &mut Formatter) -> FormatResult {
-        write!(f, "SUB R{}, ", self.dr)?;
+        match self.with_carry {
+            true => write!(f, "SBC R{}, ", self.dr)?,
+            false => write!(f, "SUB R{}, ", self.dr)?,
+        }
+
         write!(f, "R{}, ", self.sr0)?;
 
         match self.mode {
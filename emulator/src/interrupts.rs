@@ -0,0 +1,85 @@
+use shared::BranchConditions;
+
+use crate::state::State;
+
+// The handler addresses for each interrupt request line, stored in RAM
+pub const VECTOR_TABLE_ADDRESS: u16 = 0xFF00;
+pub const INTERRUPT_LINES: u8 = 16;
+
+// Controller registers
+pub const MASK_ADDRESS: u16 = 0xFF10;
+pub const PENDING_ADDRESS: u16 = 0xFF11;
+
+/**
+ * The interrupt controller, which dispatches interrupts requested by devices to the handlers in the vector table.
+ *
+ * Interrupts are delivered only while they are enabled (with EI) and unmasked (by setting the line's bit in the mask
+ * register). Entering a handler saves the PC and flags and disables interrupts, RTI restores them and re-enables
+ * interrupts. Handlers therefore don't nest.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct InterruptController {
+    pub enabled: bool,
+    pub mask: u16,
+    // The PC and flags of the interrupted code, restored by RTI
+    pub saved_pc: u16,
+    pub saved_flags: BranchConditions,
+}
+
+impl InterruptController {
+    pub fn new() -> InterruptController {
+        InterruptController {
+            enabled: false,
+            mask: 0,
+            saved_pc: 0,
+            saved_flags: BranchConditions::ZERO,
+        }
+    }
+
+    pub fn contains(address: u16) -> bool {
+        address == MASK_ADDRESS || address == PENDING_ADDRESS
+    }
+
+    pub fn load(&self, address: u16, pending: u16) -> u16 {
+        match address {
+            MASK_ADDRESS => self.mask,
+            _ => pending,
+        }
+    }
+
+    pub fn store(&mut self, address: u16, value: u16) {
+        if address == MASK_ADDRESS {
+            self.mask = value;
+        }
+    }
+}
+
+impl Default for InterruptController {
+    fn default() -> InterruptController {
+        InterruptController::new()
+    }
+}
+
+/**
+ * Enter the handler for the lowest numbered pending interrupt, if interrupts are enabled, returning its line
+ */
+pub fn dispatch(state: &mut State) -> Option<u8> {
+    if !state.interrupts.enabled || state.halt {
+        return None;
+    }
+
+    let pending = state.bus.interrupt_lines() & state.interrupts.mask;
+
+    if pending == 0 {
+        return None;
+    }
+
+    let line = pending.trailing_zeros() as u8;
+
+    state.interrupts.saved_pc = state.pc;
+    state.interrupts.saved_flags = state.flags;
+    state.interrupts.enabled = false;
+    state.pc = state.memory[(VECTOR_TABLE_ADDRESS + line as u16) as usize];
+
+    Some(line)
+}
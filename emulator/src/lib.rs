@@ -0,0 +1,102 @@
+pub mod clock;
+pub mod coverage;
+pub mod debugger;
+pub mod devices;
+pub mod history;
+pub mod instructions;
+pub mod interrupts;
+pub mod process;
+pub mod report;
+pub mod snapshot;
+pub mod state;
+mod utils;
+
+use std::time::{Duration, Instant};
+
+use history::History;
+use state::State;
+
+// Checking the time is comparatively slow, so `run` only checks its timeout every this many instructions
+const INSTRUCTIONS_PER_TIMEOUT_CHECK: u64 = 1024;
+
+/**
+ * Limits on how long `run` executes a program for
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    pub max_instructions: Option<u64>,
+    // Measured by the host's clock, regardless of whether the emulator is using virtual time
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Halted,
+    // The program was stopped by a fault, described by `State::fault`
+    Faulted,
+    InstructionLimitExceeded,
+    TimedOut,
+}
+
+/**
+ * Fetch, decode and execute a single instruction
+ */
+pub fn step(state: &mut State) {
+    History::begin_step(state);
+
+    let instruction = state.decoded_instruction(state.pc);
+
+    if let Some(coverage) = state.coverage.as_mut() {
+        coverage.record_execution(state.pc);
+    }
+
+    instruction.execute(state);
+
+    // The PC is left pointing at an instruction which faulted
+    if state.fault.is_some() {
+        return;
+    }
+
+    state.pc = state.pc.wrapping_add(1);
+
+    state.clock.execute(instruction.opcode());
+    state.bus.tick(&state.clock);
+
+    // Interrupts are taken between instructions, so the handler's first instruction is executed by the next step
+    interrupts::dispatch(state);
+}
+
+/**
+ * Execute a program until it halts, faults or exceeds one of the limits
+ */
+pub fn run(state: &mut State, limits: &Limits) -> Outcome {
+    run_counted(state, limits).0
+}
+
+/**
+ * Like `run`, also returning the number of instructions executed, including one which halted or faulted
+ */
+pub fn run_counted(state: &mut State, limits: &Limits) -> (Outcome, u64) {
+    let start = Instant::now();
+    let mut instructions = 0;
+
+    while !state.halt {
+        if limits.max_instructions.is_some_and(|max| instructions >= max) {
+            return (Outcome::InstructionLimitExceeded, instructions);
+        }
+
+        if instructions % INSTRUCTIONS_PER_TIMEOUT_CHECK == 0
+            && limits.timeout.is_some_and(|timeout| start.elapsed() >= timeout)
+        {
+            return (Outcome::TimedOut, instructions);
+        }
+
+        step(state);
+        instructions += 1;
+    }
+
+    match state.fault {
+        Some(_) => (Outcome::Faulted, instructions),
+        None => (Outcome::Halted, instructions),
+    }
+}
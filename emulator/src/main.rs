@@ -1,60 +1,320 @@
-mod instructions;
-mod state;
-mod utils;
+mod options;
+mod repl;
 
-use nix::fcntl::{fcntl, FcntlArg, OFlag};
-use nix::unistd::read;
-use state::State;
-use std::fs;
+use emulator::{
+    clock::Clock,
+    coverage::Coverage,
+    debugger::{dap::DapServer, gdb::GdbServer, listen, Debugger},
+    devices::{self, disk, display, Console, Disk, Display, InputQueue},
+    instructions::{IsaVersion, INITIAL_STACK_POINTER, STACK_POINTER},
+    process::load_arguments,
+    report,
+    snapshot::Snapshot,
+    state::State,
+    Outcome,
+};
+use options::{Options, StdinSource};
+use shared::debug_info::DebugInfo;
+use std::{
+    fs,
+    io::{self, Write},
+    process,
+};
+
+const DISPLAY_ADDRESS: u16 = 0xF000;
+const DISK_ADDRESS: u16 = 0xFD00;
+
+const EXIT_LIMIT_EXCEEDED: i32 = 124;
+const EXIT_FAULTED: i32 = 125;
 
 fn main() {
-    let binary_path = std::env::args().nth(1).expect("No binary provided");
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-    let resolved_path = std::path::Path::new(&binary_path);
+    if args.first().is_some_and(|arg| arg == "diff") {
+        process::exit(diff_snapshots(&args[1..]));
+    }
 
-    let bytes = fs::read(&resolved_path).expect("Could not read file");
+    let options = match Options::from_args(args.into_iter()) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
 
-    let mut state = State::new();
+    let debug_info = options.debug_info_path.as_ref().map(|debug_info_path| {
+        fs::read_to_string(debug_info_path)
+            .expect("Could not read debug info")
+            .parse::<DebugInfo>()
+            .expect("Invalid debug info")
+    });
 
-    bytes.chunks(2).enumerate().for_each(|(i, chunk)| {
-        let word = u16::from_be_bytes([chunk[0], chunk[1]]);
-        state.memory[i] = word;
+    let report_sections = report::parse_sections(
+        options.report_sections.as_deref().unwrap_or(report::DEFAULT_SECTIONS),
+        debug_info.as_ref(),
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
     });
 
-    state = run_program(state);
+    let mut state = State::new();
 
-    println!("\n{:?}", state);
-}
+    if let Some(binary_path) = &options.binary_path {
+        let bytes = fs::read(binary_path).expect("Could not read file");
 
-fn run_program(mut state: State) -> State {
-    let fd = 0;
-    let flags = fcntl(fd, FcntlArg::F_GETFL).expect("Failed to get flags");
+        state.load_binary(&bytes);
+        state.isa_version = options.isa_version.unwrap_or_default();
 
-    fcntl(
-        fd,
-        FcntlArg::F_SETFL(OFlag::from_bits_truncate(flags) | OFlag::O_NONBLOCK),
-    )
-    .expect("Failed to set non-blocking mode");
+        if state.isa_version == IsaVersion::V2 {
+            state.registers[STACK_POINTER as usize] = INITIAL_STACK_POINTER;
+        }
+
+        if let Err(e) = load_arguments(&mut state, &options.arguments, bytes.len().div_ceil(2)) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+    state.clock = Clock::new(options.time_mode);
+
+    for (opcode, cycles) in &options.cycle_costs {
+        state.clock.set_cycle_cost(*opcode, *cycles);
+    }
+
+    // Under the debugger stdin is used for commands, so input for the program is supplied through a queue instead
+    let input = InputQueue::default();
+
+    let scripted_input = match &options.stdin {
+        Some(StdinSource::File(path)) => Some(fs::read(path).unwrap_or_else(|e| {
+            eprintln!("Could not read {}: {}", path, e);
+            process::exit(1);
+        })),
+        Some(StdinSource::String(string)) => Some(string.as_bytes().to_vec()),
+        None => None,
+    };
+
+    let console = match scripted_input {
+        Some(bytes) => {
+            input.push(&bytes);
+            input.close();
+
+            Console::from_queue(input.clone())
+        }
+        None if options.debugger_enabled() => Console::from_queue(input.clone()),
+        None => Console::host(),
+    };
+
+    let console = match &options.stdout_path {
+        Some(path) => console.with_output_file(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        }),
+        None => console,
+    };
+
+    devices::map_standard_devices(&mut state.bus, console);
+
+    let display = match &options.display_path {
+        Some(path) => Some(Display::file(path)),
+        None if options.display => Some(Display::terminal()),
+        None => None,
+    };
+
+    if let Some(display) = display {
+        state.bus.map(
+            DISPLAY_ADDRESS..=DISPLAY_ADDRESS + display::SIZE - 1,
+            Box::new(display),
+            None,
+        );
+    }
+
+    if let Some(disk_path) = &options.disk_path {
+        let disk = Disk::open(disk_path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+
+        state
+            .bus
+            .map(DISK_ADDRESS..=DISK_ADDRESS + disk::SIZE - 1, Box::new(disk), None);
+    }
+
+    for specification in &options.devices {
+        let mapping = devices::from_specification(specification).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+
+        state.bus.map(mapping.range, mapping.device, mapping.interrupt_line);
+    }
+
+    // Devices are restored too, so the snapshot can only be resumed once they are all mapped
+    if let Some(snapshot_path) = &options.load_snapshot_path {
+        if let Err(e) = Snapshot::load(snapshot_path).and_then(|snapshot| snapshot.restore(&mut state)) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+
+        if options.isa_version.is_some_and(|version| version != state.isa_version) {
+            eprintln!("The snapshot was saved with ISA version {}", state.isa_version as u16);
+            process::exit(1);
+        }
+    }
+
+    if options.coverage_enabled() {
+        state.coverage = Some(Coverage::new());
+    }
+
+    let outcome = match options.debugger_enabled() || options.gdb_address.is_some() || options.dap_address.is_some() {
+        true => {
+            state = match (&options.gdb_address, &options.dap_address) {
+                (Some(address), _) => run_gdb_server(state, address, &options),
+                (_, Some(address)) => run_dap_server(state, address, &options, debug_info.as_ref()),
+                _ => repl::run(state, &options, debug_info.as_ref(), input),
+            }
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+
+            match state.fault {
+                Some(_) => Outcome::Faulted,
+                None => Outcome::Halted,
+            }
+        }
+        false => emulator::run(&mut state, &options.limits),
+    };
+
+    if let Some(snapshot_path) = &options.save_snapshot_path {
+        if let Err(e) = Snapshot::capture(&state).save(snapshot_path) {
+            eprintln!("{}", e);
+        }
+    }
+
+    state.bus.finish();
+
+    if !options.quiet {
+        let report = report::render(&state, &report_sections, options.report_format);
+
+        match &options.report_path {
+            Some(path) => fs::write(path, report).unwrap_or_else(|e| eprintln!("Could not write {}: {}", path, e)),
+            // Like the console's output, the report is dropped if stdout has been closed
+            None => {
+                let _ = write!(io::stdout(), "\n{}", report);
+            }
+        }
+    }
+
+    if let (Some(coverage), Some(debug_info)) = (&state.coverage, &debug_info) {
+        if let Some(lcov_path) = &options.coverage_lcov_path {
+            fs::write(lcov_path, coverage.to_lcov(debug_info, &state.memory)).expect("Could not write lcov report");
+        }
+
+        if let Some(report_path) = &options.coverage_report_path {
+            let report = coverage
+                .to_annotated_report(debug_info, &state.memory)
+                .expect("Could not generate coverage report");
+
+            fs::write(report_path, report).expect("Could not write coverage report");
+        }
+    }
+
+    let exit_code = match outcome {
+        Outcome::Halted => state.exit_status.unwrap_or(0) as i32,
+        Outcome::Faulted => {
+            eprintln!(
+                "Program faulted at {:04X}: {}",
+                state.pc,
+                state.fault.as_deref().unwrap_or_default()
+            );
 
-    // Skip the SLT
-    state.pc = state.memory[0] + 1;
+            EXIT_FAULTED
+        }
+        Outcome::InstructionLimitExceeded => {
+            eprintln!(
+                "Program exceeded the limit of {} instructions",
+                options.limits.max_instructions.unwrap_or_default()
+            );
 
-    while !state.halt {
-        let mut stdin_buffer = [0u8; 1024];
+            EXIT_LIMIT_EXCEEDED
+        }
+        Outcome::TimedOut => {
+            eprintln!(
+                "Program timed out after {}ms",
+                options.limits.timeout.unwrap_or_default().as_millis()
+            );
 
-        match read(fd, &mut stdin_buffer) {
-            Ok(_) => state.stdin.append(&mut stdin_buffer.to_vec()),
-            Err(_) => {}
+            EXIT_LIMIT_EXCEEDED
         }
+    };
+
+    process::exit(exit_code);
+}
+
+/**
+ * Let GDB debug the program until it detaches or disconnects
+ */
+fn run_gdb_server(state: State, address: &str, options: &Options) -> Result<State, String> {
+    let (reader, writer) = listen(address, "GDB")?;
+    let mut debugger = Debugger::new(state);
 
-        let instruction = instructions::from_machine_code(state.memory[state.pc as usize]);
+    if options.history_limit > 0 {
+        debugger.enable_history(options.history_limit);
+    }
+
+    GdbServer::new(&mut debugger, reader, writer).run()?;
 
-        // println!("{:?}", instruction);
+    Ok(debugger.state)
+}
 
-        instruction.execute(&mut state);
+/**
+ * Let an editor debug the program through the Debug Adapter Protocol until it disconnects
+ */
+fn run_dap_server(
+    state: State,
+    address: &str,
+    options: &Options,
+    debug_info: Option<&DebugInfo>,
+) -> Result<State, String> {
+    let (reader, writer) = listen(address, "a debug adapter client")?;
+    let mut debugger = Debugger::new(state);
 
-        state.pc = state.pc.wrapping_add(1);
+    if options.history_limit > 0 {
+        debugger.enable_history(options.history_limit);
     }
 
-    return state;
+    DapServer::new(&mut debugger, debug_info, reader, writer).run()?;
+
+    Ok(debugger.state)
+}
+
+/**
+ * Compare two snapshots, returning the exit code: 0 if they are the same, 1 if they differ and 2 on error
+ */
+fn diff_snapshots(args: &[String]) -> i32 {
+    let [old_path, new_path] = args else {
+        eprintln!("Usage: emulator diff OLD NEW");
+        return 2;
+    };
+
+    let snapshots = Snapshot::load(old_path).and_then(|old| Ok((old, Snapshot::load(new_path)?)));
+
+    let (old, new) = match snapshots {
+        Ok(snapshots) => snapshots,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+
+    let differences = old.diff(&new);
+
+    for difference in &differences {
+        println!("{}", difference);
+    }
+
+    match differences.is_empty() {
+        true => 0,
+        false => 1,
+    }
 }
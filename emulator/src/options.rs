@@ -0,0 +1,264 @@
+use std::time::Duration;
+
+use emulator::{
+    clock::TimeMode,
+    instructions::{IsaVersion, OPCODES},
+    report::ReportFormat,
+    Limits,
+};
+
+// The default number of instructions the debugger records so they can be stepped backwards
+const DEFAULT_HISTORY_LIMIT: usize = 1_000_000;
+
+// Where the program's input comes from instead of the host's stdin
+pub enum StdinSource {
+    File(String),
+    String(String),
+}
+
+pub struct Options {
+    // Either a binary or a snapshot to resume is given
+    pub binary_path: Option<String>,
+    pub load_snapshot_path: Option<String>,
+    // Save a snapshot once the program stops
+    pub save_snapshot_path: Option<String>,
+    pub debug_info_path: Option<String>,
+    pub coverage_lcov_path: Option<String>,
+    pub coverage_report_path: Option<String>,
+    pub debug: bool,
+    // Serve the GDB remote protocol on this address instead of running the interactive debugger
+    pub gdb_address: Option<String>,
+    // Serve the Debug Adapter Protocol on this address instead of running the interactive debugger
+    pub dap_address: Option<String>,
+    pub breakpoints: Vec<String>,
+    pub watchpoints: Vec<String>,
+    pub history_limit: usize,
+    // Additional devices of the form KIND@START[..END][:ARGS]
+    pub devices: Vec<String>,
+    pub display: bool,
+    // Write the display to a file instead of drawing it in the terminal
+    pub display_path: Option<String>,
+    pub disk_path: Option<String>,
+    pub time_mode: TimeMode,
+    // Only set when given, as a snapshot's version is used otherwise
+    pub isa_version: Option<IsaVersion>,
+    // The number of cycles executing an instruction with each opcode takes, if not the default
+    pub cycle_costs: Vec<(u8, u16)>,
+    pub stdin: Option<StdinSource>,
+    pub stdout_path: Option<String>,
+    pub limits: Limits,
+    // Don't report the state of the machine once the program stops
+    pub quiet: bool,
+    // A comma separated list of the sections to report, if not the default
+    pub report_sections: Option<String>,
+    pub report_format: ReportFormat,
+    // Write the report to a file instead of stdout
+    pub report_path: Option<String>,
+    // Command line arguments for the program, given after --
+    pub arguments: Vec<String>,
+}
+
+impl Options {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut binary_path = None;
+        let mut load_snapshot_path = None;
+        let mut save_snapshot_path = None;
+        let mut debug_info_path = None;
+        let mut coverage_lcov_path = None;
+        let mut coverage_report_path = None;
+        let mut debug = false;
+        let mut gdb_address = None;
+        let mut dap_address = None;
+        let mut breakpoints = Vec::new();
+        let mut watchpoints = Vec::new();
+        let mut history_limit = DEFAULT_HISTORY_LIMIT;
+        let mut devices = Vec::new();
+        let mut display = false;
+        let mut display_path = None;
+        let mut disk_path = None;
+        let mut time_mode = TimeMode::Virtual;
+        let mut isa_version = None;
+        let mut cycle_costs = Vec::new();
+        let mut stdin = None;
+        let mut stdout_path = None;
+        let mut limits = Limits::default();
+        let mut quiet = false;
+        let mut report_sections: Option<String> = None;
+        let mut report_format = ReportFormat::default();
+        let mut report_path = None;
+        let mut arguments = Vec::new();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--" => {
+                    arguments.extend(args.by_ref());
+                    break;
+                }
+                "--debug-info" => debug_info_path = Some(next_value(&mut args, &arg)?),
+                "--coverage-lcov" => coverage_lcov_path = Some(next_value(&mut args, &arg)?),
+                "--coverage-report" => coverage_report_path = Some(next_value(&mut args, &arg)?),
+                "--debug" => debug = true,
+                "--gdb" => gdb_address = Some(next_value(&mut args, &arg)?),
+                "--dap" => dap_address = Some(next_value(&mut args, &arg)?),
+                "--break" => breakpoints.push(next_value(&mut args, &arg)?),
+                "--watch" => watchpoints.push(next_value(&mut args, &arg)?),
+                "--device" => devices.push(next_value(&mut args, &arg)?),
+                "--display" => display = true,
+                "--display-file" => display_path = Some(next_value(&mut args, &arg)?),
+                "--disk" => disk_path = Some(next_value(&mut args, &arg)?),
+                "--realtime" => time_mode = TimeMode::Realtime,
+                "--isa-version" => isa_version = Some(next_value(&mut args, &arg)?.parse()?),
+                "--cycle-cost" => cycle_costs.push(parse_cycle_cost(&next_value(&mut args, &arg)?)?),
+                "--stdin-file" | "--stdin-string" if stdin.is_some() => {
+                    return Err("Only one of --stdin-file and --stdin-string can be given".to_string())
+                }
+                "--stdin-file" => stdin = Some(StdinSource::File(next_value(&mut args, &arg)?)),
+                "--stdin-string" => stdin = Some(StdinSource::String(next_value(&mut args, &arg)?)),
+                "--stdout-file" => stdout_path = Some(next_value(&mut args, &arg)?),
+                "--quiet" => quiet = true,
+                "--report" => {
+                    let sections = next_value(&mut args, &arg)?;
+
+                    report_sections = Some(match report_sections {
+                        Some(existing) => existing + "," + &sections,
+                        None => sections,
+                    });
+                }
+                "--report-format" => report_format = next_value(&mut args, &arg)?.parse()?,
+                "--report-file" => report_path = Some(next_value(&mut args, &arg)?),
+                "--load-snapshot" => load_snapshot_path = Some(next_value(&mut args, &arg)?),
+                "--save-snapshot" => save_snapshot_path = Some(next_value(&mut args, &arg)?),
+                "--max-instructions" => {
+                    let value = next_value(&mut args, &arg)?;
+
+                    limits.max_instructions = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid instruction limit \"{}\"", value))?,
+                    );
+                }
+                "--timeout-ms" => {
+                    let value = next_value(&mut args, &arg)?;
+
+                    limits.timeout = Some(Duration::from_millis(
+                        value.parse().map_err(|_| format!("Invalid timeout \"{}\"", value))?,
+                    ));
+                }
+                "--history-limit" => {
+                    let value = next_value(&mut args, &arg)?;
+
+                    history_limit = value
+                        .parse()
+                        .map_err(|_| format!("Invalid history limit \"{}\"", value))?;
+                }
+                _ if arg.starts_with("--") => return Err(format!("Unrecognized option {}", arg)),
+                _ if binary_path.is_none() => binary_path = Some(arg),
+                _ => return Err(format!("Unexpected argument {}", arg)),
+            }
+        }
+
+        let options = Options {
+            binary_path,
+            load_snapshot_path,
+            save_snapshot_path,
+            debug_info_path,
+            coverage_lcov_path,
+            coverage_report_path,
+            debug,
+            gdb_address,
+            dap_address,
+            breakpoints,
+            watchpoints,
+            history_limit,
+            devices,
+            display,
+            display_path,
+            disk_path,
+            time_mode,
+            isa_version,
+            cycle_costs,
+            stdin,
+            stdout_path,
+            limits,
+            quiet,
+            report_sections,
+            report_format,
+            report_path,
+            arguments,
+        };
+
+        match (&options.binary_path, &options.load_snapshot_path) {
+            (None, None) => return Err("No binary provided".to_string()),
+            (Some(_), Some(_)) => return Err("A binary can't be given when resuming a snapshot".to_string()),
+            (None, Some(_)) if !options.arguments.is_empty() => {
+                return Err("Arguments can't be given when resuming a snapshot".to_string())
+            }
+            _ => {}
+        }
+
+        if options.gdb_address.is_some() && options.dap_address.is_some() {
+            return Err("Only one of --gdb and --dap can be given".to_string());
+        }
+
+        if options.gdb_address.is_some() && options.debugger_enabled() {
+            return Err("--gdb can't be combined with --debug, --break or --watch".to_string());
+        }
+
+        if options.dap_address.is_some() && options.debugger_enabled() {
+            return Err("--dap can't be combined with --debug, --break or --watch".to_string());
+        }
+
+        // Debugged programs run under the debugger's control, which has no notion of limits
+        let limited = options.limits.max_instructions.is_some() || options.limits.timeout.is_some();
+        let debugged = options.debugger_enabled() || options.gdb_address.is_some() || options.dap_address.is_some();
+
+        if limited && debugged {
+            return Err(
+                "--max-instructions and --timeout-ms can't be combined with --debug, --break, --watch, --gdb or --dap"
+                    .to_string(),
+            );
+        }
+
+        if options.coverage_enabled() && options.debug_info_path.is_none() {
+            return Err("Coverage reports require debug info (--debug-info)".to_string());
+        }
+
+        Ok(options)
+    }
+
+    /**
+     * Whether the program should be run under the interactive debugger
+     */
+    pub fn debugger_enabled(&self) -> bool {
+        self.debug || !self.breakpoints.is_empty() || !self.watchpoints.is_empty()
+    }
+
+    pub fn coverage_enabled(&self) -> bool {
+        self.coverage_lcov_path.is_some() || self.coverage_report_path.is_some()
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+    args.next().ok_or(format!("No value provided for {}", option))
+}
+
+/**
+ * Parse a cycle cost of the form `MNEMONIC=CYCLES`, which applies to every instruction sharing the mnemonic's opcode
+ */
+fn parse_cycle_cost(value: &str) -> Result<(u8, u16), String> {
+    let (mnemonic, cycles) = value
+        .split_once('=')
+        .ok_or(format!("Invalid cycle cost \"{}\", expected MNEMONIC=CYCLES", value))?;
+
+    let opcode = OPCODES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(mnemonic))
+        .map(|(_, opcode)| *opcode)
+        .ok_or(format!("Unrecognized instruction \"{}\"", mnemonic))?;
+
+    let cycles = cycles
+        .parse()
+        .map_err(|_| format!("Invalid number of cycles \"{}\"", cycles))?;
+
+    Ok((opcode, cycles))
+}
@@ -0,0 +1,59 @@
+use crate::state::State;
+
+// Storing a value here halts the program with the value as its exit status
+pub const EXIT_ADDRESS: u16 = 0xFFFB;
+// Exit statuses above this are reserved for the emulator's own exit codes, e.g. for faults
+pub const MAX_EXIT_STATUS: u16 = 123;
+
+// The region holding the program's command line arguments
+pub const ARGUMENTS_ADDRESS: u16 = 0xE000;
+pub const ARGUMENTS_END: u16 = 0xEFFF;
+
+/**
+ * Store command line arguments for the program. The first word of the region is the number of arguments, followed by
+ * the absolute address of each argument, followed by the arguments themselves as null terminated strings with one
+ * byte per word (the same layout as the ASCII directive).
+ *
+ * `image_length` is the number of words the loaded binary occupies. Without arguments the region is left untouched,
+ * so a binary may extend into it, otherwise the binary must end before the region.
+ */
+pub fn load_arguments(state: &mut State, arguments: &[String], image_length: usize) -> Result<(), String> {
+    if arguments.is_empty() {
+        return Ok(());
+    }
+
+    if image_length > ARGUMENTS_ADDRESS as usize {
+        return Err(format!(
+            "The program is {} words long and overlaps the arguments at {:04X}, so it can't be given arguments",
+            image_length, ARGUMENTS_ADDRESS
+        ));
+    }
+
+    let mut words = vec![arguments.len() as u16];
+    let mut strings = Vec::new();
+    // The strings follow the count and the pointers
+    let strings_address = ARGUMENTS_ADDRESS as usize + 1 + arguments.len();
+
+    for argument in arguments {
+        words.push((strings_address + strings.len()) as u16);
+        strings.extend(argument.bytes().map(|byte| byte as u16));
+        strings.push(0);
+    }
+
+    words.extend(strings);
+
+    if words.len() > (ARGUMENTS_END - ARGUMENTS_ADDRESS + 1) as usize {
+        return Err(format!(
+            "The arguments take {} words, which doesn't fit in the {} words available",
+            words.len(),
+            ARGUMENTS_END - ARGUMENTS_ADDRESS + 1
+        ));
+    }
+
+    for (offset, word) in words.iter().enumerate() {
+        state.memory[ARGUMENTS_ADDRESS as usize + offset] = *word;
+        state.invalidate_decoded(ARGUMENTS_ADDRESS + offset as u16);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,431 @@
+use std::{
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+use emulator::{
+    debugger::{
+        parse_number, resolve_location, resolve_range, Condition, Debugger, MemoryAccess, StopReason, WatchKind,
+    },
+    devices::InputQueue,
+    snapshot::Snapshot,
+    state::State,
+};
+use nix::sys::signal::{signal, SigHandler, Signal};
+use shared::debug_info::DebugInfo;
+
+use crate::options::Options;
+
+const HELP: &str = "Commands:
+  c, continue                 Continue until a breakpoint, watchpoint, halt or Ctrl-C
+  s, step [N]                 Execute N instructions (default 1)
+  b, break LOC [if COND]      Break before executing the instruction at LOC (an address, .LABEL or file.asm:LINE)
+  w, watch [r|w|rw] RANGE     Stop after the program reads/writes an address or range (START..END, default rw)
+  rs, reverse-step [N]        Undo the last N instructions (default 1)
+  rc, reverse-continue        Step backwards until a breakpoint or write watchpoint
+  last-write ADDR             Step backwards to just before the last write to ADDR
+  checkpoint                  Save the current position so it can be returned to with rewind
+  checkpoints                 List checkpoints
+  rewind ID                   Step backwards to a checkpoint
+  d, delete ID                Delete a breakpoint or watchpoint
+  l, list                     List breakpoints and watchpoints
+  r, regs                     Print registers, flags and the call stack
+  x ADDR [COUNT]              Print COUNT words of memory starting at ADDR
+  devices                     List the devices mapped into memory
+  save PATH                   Save a snapshot of the machine to PATH
+  load PATH                   Resume from a snapshot saved to PATH, discarding history
+  input TEXT                  Append TEXT to the program's stdin (\\n is a newline)
+  eof                         Close the program's stdin, so it reaches the end of input once it has read everything
+  q, quit                     Stop debugging
+";
+
+// The interrupt handle of the running debugger, set from the SIGINT handler so Ctrl-C stops execution
+static INTERRUPT_HANDLE: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+extern "C" fn handle_sigint(_: i32) {
+    if let Some(interrupt_handle) = INTERRUPT_HANDLE.get() {
+        interrupt_handle.store(true, Ordering::Relaxed);
+    }
+}
+
+enum CommandResult {
+    Continue,
+    Quit,
+}
+
+/**
+ * Run a program under an interactive debugger. Commands are read from stdin, so the program's stdin is supplied
+ * with the `input` command.
+ */
+pub fn run(
+    state: State,
+    options: &Options,
+    debug_info: Option<&DebugInfo>,
+    input: InputQueue,
+) -> Result<State, String> {
+    let mut debugger = Debugger::new(state);
+
+    if options.history_limit > 0 {
+        debugger.enable_history(options.history_limit);
+    }
+
+    if INTERRUPT_HANDLE.set(debugger.interrupt_handle()).is_ok() {
+        unsafe { signal(Signal::SIGINT, SigHandler::Handler(handle_sigint)) }.expect("Failed to set SIGINT handler");
+    }
+
+    for breakpoint in &options.breakpoints {
+        add_breakpoint(&mut debugger, breakpoint, debug_info)?;
+    }
+
+    for watchpoint in &options.watchpoints {
+        add_watchpoint(&mut debugger, watchpoint, debug_info)?;
+    }
+
+    match options.debug {
+        true => eprintln!("Stopped at {}", describe_address(debugger.state.pc, debug_info)),
+        false => {
+            let stop_reason = debugger.continue_execution();
+            report_stop(&debugger, stop_reason, debug_info);
+        }
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        eprint!("(cal) ");
+        io::stderr().flush().unwrap();
+
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+
+        match execute_command(&mut debugger, line.trim(), debug_info, &input) {
+            Ok(CommandResult::Continue) => {}
+            Ok(CommandResult::Quit) => break,
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    Ok(debugger.state)
+}
+
+fn execute_command(
+    debugger: &mut Debugger,
+    command: &str,
+    debug_info: Option<&DebugInfo>,
+    input: &InputQueue,
+) -> Result<CommandResult, String> {
+    let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+    let args = args.trim();
+
+    match name {
+        "" => {}
+        "c" | "continue" => {
+            let stop_reason = debugger.continue_execution();
+            report_stop(debugger, stop_reason, debug_info);
+        }
+        "s" | "step" => {
+            let count = match args {
+                "" => 1,
+                _ => args.parse().map_err(|_| format!("Invalid step count \"{}\"", args))?,
+            };
+
+            let mut stop_reason = StopReason::Step;
+
+            for _ in 0..count {
+                stop_reason = debugger.step();
+
+                if !matches!(stop_reason, StopReason::Step) {
+                    break;
+                }
+            }
+
+            report_stop(debugger, stop_reason, debug_info);
+        }
+        "rs" | "reverse-step" => {
+            let count = match args {
+                "" => 1,
+                _ => args.parse().map_err(|_| format!("Invalid step count \"{}\"", args))?,
+            };
+
+            let mut stop_reason = StopReason::Step;
+
+            for _ in 0..count {
+                stop_reason = debugger.step_back();
+
+                if !matches!(stop_reason, StopReason::Step) {
+                    break;
+                }
+            }
+
+            report_stop(debugger, stop_reason, debug_info);
+        }
+        "rc" | "reverse-continue" => {
+            let stop_reason = debugger.reverse_continue();
+            report_stop(debugger, stop_reason, debug_info);
+        }
+        "last-write" => {
+            let address = resolve_location(args, debug_info)?;
+            let stop_reason = debugger.run_back_to_write(address);
+
+            if let StopReason::Step = stop_reason {
+                eprintln!("The next instruction writes {:04X}", address);
+            }
+
+            report_stop(debugger, stop_reason, debug_info);
+        }
+        "checkpoint" => {
+            let id = debugger.add_checkpoint()?;
+
+            eprintln!(
+                "Checkpoint {} at {}",
+                id,
+                describe_address(debugger.state.pc, debug_info)
+            );
+        }
+        "checkpoints" => {
+            if let Some(history) = &debugger.state.history {
+                for (id, position) in history.checkpoints() {
+                    eprintln!("{}: after {} instructions", id, position);
+                }
+            }
+        }
+        "rewind" => {
+            let id = args
+                .parse()
+                .map_err(|_| format!("Invalid checkpoint id \"{}\"", args))?;
+
+            debugger.rewind(id)?;
+            report_stop(debugger, StopReason::Step, debug_info);
+        }
+        "b" | "break" => add_breakpoint(debugger, args, debug_info)?,
+        "w" | "watch" => add_watchpoint(debugger, args, debug_info)?,
+        "d" | "delete" => {
+            let id = args.parse().map_err(|_| format!("Invalid id \"{}\"", args))?;
+
+            if !debugger.remove(id) {
+                return Err(format!("No breakpoint or watchpoint with id {}", id));
+            }
+        }
+        "l" | "list" => {
+            for breakpoint in debugger.breakpoints() {
+                eprint!(
+                    "{}: break {}",
+                    breakpoint.id,
+                    describe_address(breakpoint.address, debug_info)
+                );
+
+                match &breakpoint.condition {
+                    Some(condition) => eprintln!(" if {}", condition),
+                    None => eprintln!(),
+                }
+            }
+
+            for watchpoint in debugger.watchpoints() {
+                eprintln!(
+                    "{}: watch {} {:04X}..{:04X}",
+                    watchpoint.id,
+                    watchpoint.kind,
+                    watchpoint.range.start(),
+                    *watchpoint.range.end() as u32 + 1
+                );
+            }
+        }
+        "r" | "regs" => print_registers(&debugger.state, debug_info),
+        "x" => {
+            let mut parts = args.split_whitespace();
+            let address = resolve_location(parts.next().ok_or("No address provided")?, debug_info)?;
+            let count = match parts.next() {
+                Some(count) => parse_number(count)?,
+                None => 1,
+            };
+
+            for row_start in (0..count).step_by(8) {
+                let row_address = address.wrapping_add(row_start);
+
+                eprint!("{:04X}:", row_address);
+
+                for offset in row_start..count.min(row_start + 8) {
+                    eprint!(" {:04X}", debugger.state.memory[address.wrapping_add(offset) as usize]);
+                }
+
+                eprintln!();
+            }
+        }
+        "devices" => {
+            for mapping in debugger.state.bus.mappings() {
+                eprintln!(
+                    "{:04X}..{:04X}: {}",
+                    mapping.range.start(),
+                    *mapping.range.end() as u32 + 1,
+                    mapping.device.name()
+                );
+            }
+        }
+        "save" => {
+            Snapshot::capture(&debugger.state).save(args)?;
+            eprintln!("Saved a snapshot to {}", args);
+        }
+        "load" => {
+            Snapshot::load(args)?.restore(&mut debugger.state)?;
+            report_stop(debugger, StopReason::Step, debug_info);
+        }
+        "input" => {
+            if input.lock().closed {
+                return Err("The program's stdin has already been closed".to_string());
+            }
+
+            input.push(args.replace(r"\n", "\n").as_bytes());
+        }
+        "eof" => input.close(),
+        "h" | "help" => eprint!("{}", HELP),
+        "q" | "quit" => return Ok(CommandResult::Quit),
+        _ => {
+            return Err(format!(
+                "Unrecognized command \"{}\", type help for a list of commands",
+                name
+            ))
+        }
+    }
+
+    Ok(CommandResult::Continue)
+}
+
+/**
+ * Add a breakpoint from a specification of the form `LOC [if COND]`
+ */
+fn add_breakpoint(debugger: &mut Debugger, specification: &str, debug_info: Option<&DebugInfo>) -> Result<(), String> {
+    let (location, condition) = match specification.split_once(" if ") {
+        Some((location, condition)) => (location, Some(Condition::parse(condition)?)),
+        None => (specification, None),
+    };
+
+    let address = resolve_location(location.trim(), debug_info)?;
+    let id = debugger.add_breakpoint(address, condition);
+
+    eprintln!("Breakpoint {} at {}", id, describe_address(address, debug_info));
+
+    Ok(())
+}
+
+/**
+ * Add a watchpoint from a specification of the form `[r|w|rw] RANGE`
+ */
+fn add_watchpoint(debugger: &mut Debugger, specification: &str, debug_info: Option<&DebugInfo>) -> Result<(), String> {
+    let (kind, range) = match specification.split_once(' ') {
+        Some((kind, range)) => (WatchKind::parse(kind)?, range.trim()),
+        None => (WatchKind::ReadWrite, specification),
+    };
+
+    let range = resolve_range(range, debug_info)?;
+    let id = debugger.add_watchpoint(range.clone(), kind);
+
+    eprintln!(
+        "Watchpoint {} ({}) on {:04X}..{:04X}",
+        id,
+        kind,
+        range.start(),
+        *range.end() as u32 + 1
+    );
+
+    Ok(())
+}
+
+fn report_stop(debugger: &Debugger, stop_reason: StopReason, debug_info: Option<&DebugInfo>) {
+    let location = describe_address(debugger.state.pc, debug_info);
+
+    match stop_reason {
+        StopReason::Halted => eprintln!("Program halted"),
+        StopReason::Faulted => eprintln!(
+            "Program faulted at {}: {}",
+            location,
+            debugger.state.fault.as_deref().unwrap_or_default()
+        ),
+        StopReason::Step => eprintln!("Stopped at {}", location),
+        StopReason::Breakpoint(id) => eprintln!("Breakpoint {} hit at {}", id, location),
+        StopReason::Interrupted => eprintln!("Interrupted at {}", location),
+        StopReason::HistoryExhausted => eprintln!("Reached the start of recorded history at {}", location),
+        StopReason::Watchpoint(hit) => {
+            match hit.access {
+                MemoryAccess::Read => eprintln!(
+                    "Watchpoint {}: {} read {:04X} (value {:04X})",
+                    hit.id,
+                    describe_address(hit.pc, debug_info),
+                    hit.address,
+                    hit.new_value
+                ),
+                MemoryAccess::Write => eprintln!(
+                    "Watchpoint {}: {} wrote {:04X} (old value {:04X}, new value {:04X})",
+                    hit.id,
+                    describe_address(hit.pc, debug_info),
+                    hit.address,
+                    hit.old_value,
+                    hit.new_value
+                ),
+            }
+
+            eprintln!("Stopped at {}", location);
+        }
+    }
+}
+
+fn print_registers(state: &State, debug_info: Option<&DebugInfo>) {
+    for (register, value) in state.registers.iter().enumerate() {
+        eprintln!("R{}: {:04X} ({})", register, value, *value as i16);
+    }
+
+    eprintln!("PC: {}", describe_address(state.pc, debug_info));
+    eprintln!("Flags: {}", state.flags.as_string());
+    eprintln!(
+        "Interrupts: {} (mask {:04X}, pending {:04X})",
+        if state.interrupts.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        },
+        state.interrupts.mask,
+        state.bus.interrupt_lines()
+    );
+    eprintln!(
+        "Time: {} cycles, {} ms",
+        state.clock.cycles(),
+        state.clock.milliseconds()
+    );
+
+    for i in (0..state.call_stack_pointer).rev() {
+        // Call stack entries point at the CALL instruction
+        eprintln!(
+            "  called from {}",
+            describe_address(state.call_stack[i as usize], debug_info)
+        );
+    }
+}
+
+/**
+ * Describe an address with the closest preceding label and the source line it was assembled from
+ */
+pub fn describe_address(address: u16, debug_info: Option<&DebugInfo>) -> String {
+    let mut description = format!("{:04X}", address);
+
+    if let Some(debug_info) = debug_info {
+        if let Some((label, label_address)) = debug_info.label_before(address) {
+            match address - label_address {
+                0 => description += &format!(" <.{}>", label),
+                offset => description += &format!(" <.{}+{}>", label, offset),
+            }
+        }
+
+        if let Some(statement) = debug_info.statement_at(address) {
+            let location = statement.outermost_location();
+
+            description += &format!(" {}:{}", debug_info.files[location.file], location.line);
+        }
+    }
+
+    description
+}
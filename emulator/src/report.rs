@@ -0,0 +1,293 @@
+use std::{fmt::Write, ops::RangeInclusive, str::FromStr};
+
+use serde_json::{json, Map, Value};
+use shared::debug_info::DebugInfo;
+
+use crate::{debugger::resolve_range, state::State};
+
+// The sections reported when none are chosen, the same as `Debug for State`
+pub const DEFAULT_SECTIONS: &str = "registers,flags,call-stack,memory";
+
+const WORDS_PER_LINE: usize = 16;
+
+/**
+ * A part of the machine's state to report
+ */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Section {
+    // R0-R7 and the PC
+    Registers,
+    // The flags and whether the program halted, faulted or exited
+    Flags,
+    CallStack,
+    Memory {
+        // The label the range was chosen by, if any
+        label: Option<String>,
+        range: RangeInclusive<u16>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ReportFormat {
+    #[default]
+    Human,
+    // Values in hex, with an ASCII column alongside memory
+    Hexdump,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<ReportFormat, String> {
+        match format {
+            "human" => Ok(ReportFormat::Human),
+            "hexdump" => Ok(ReportFormat::Hexdump),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(format!(
+                "Unrecognized report format \"{}\", expected human, hexdump or json",
+                format
+            )),
+        }
+    }
+}
+
+/**
+ * Parse a comma separated list of sections: `registers`, `flags`, `call-stack`, `memory` (all of memory),
+ * `memory=RANGE` (a range as accepted by the debugger, e.g. `0x100..0x120` or `.BUFFER...BUFFER_END`) and
+ * `label=.LABEL` (the words from a label up to the next label)
+ */
+pub fn parse_sections(sections: &str, debug_info: Option<&DebugInfo>) -> Result<Vec<Section>, String> {
+    sections
+        .split(',')
+        .map(|section| match section.split_once('=') {
+            None => match section {
+                "registers" => Ok(Section::Registers),
+                "flags" => Ok(Section::Flags),
+                "call-stack" => Ok(Section::CallStack),
+                "memory" => Ok(Section::Memory {
+                    label: None,
+                    range: 0..=u16::MAX,
+                }),
+                _ => Err(format!("Unrecognized report section \"{}\"", section)),
+            },
+            Some(("memory", range)) => Ok(Section::Memory {
+                label: None,
+                range: resolve_range(range, debug_info)?,
+            }),
+            Some(("label", label)) => Ok(Section::Memory {
+                label: Some(label.to_string()),
+                range: label_region(label, debug_info.ok_or("Reporting labels requires debug info")?)?,
+            }),
+            _ => Err(format!("Unrecognized report section \"{}\"", section)),
+        })
+        .collect()
+}
+
+/**
+ * The words from a label up to the next label at a higher address, or the end of the program
+ */
+fn label_region(label: &str, debug_info: &DebugInfo) -> Result<RangeInclusive<u16>, String> {
+    let start = label
+        .strip_prefix('.')
+        .and_then(|name| debug_info.label_address(name))
+        .ok_or(format!("Unrecognized label {}", label))?;
+
+    let end = debug_info
+        .labels
+        .iter()
+        .map(|(_, address)| *address)
+        .find(|address| *address > start)
+        .or(debug_info
+            .statements
+            .last()
+            .map(|statement| statement.address.wrapping_add(statement.width)))
+        .filter(|end| *end > start)
+        .ok_or(format!("{} doesn't label anything", label))?;
+
+    Ok(start..=end - 1)
+}
+
+pub fn render(state: &State, sections: &[Section], format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Json => {
+            let mut report = Map::new();
+            let mut memory = Vec::new();
+
+            for section in sections {
+                match section {
+                    Section::Registers => {
+                        report.insert("registers".to_string(), json_registers(state));
+                    }
+                    Section::Flags => {
+                        report.insert("flags".to_string(), json_flags(state));
+                    }
+                    Section::CallStack => {
+                        report.insert("call_stack".to_string(), json!(call_stack(state)));
+                    }
+                    Section::Memory { label, range } => memory.push(json!({
+                        "label": label,
+                        "start": range.start(),
+                        "end": *range.end() as u32 + 1,
+                        "words": state.memory[*range.start() as usize..=*range.end() as usize],
+                    })),
+                }
+            }
+
+            if !memory.is_empty() {
+                report.insert("memory".to_string(), Value::Array(memory));
+            }
+
+            serde_json::to_string_pretty(&Value::Object(report)).unwrap() + "\n"
+        }
+        _ => {
+            let mut report = String::new();
+
+            for section in sections {
+                render_text_section(&mut report, state, section, format);
+            }
+
+            report
+        }
+    }
+}
+
+fn render_text_section(out: &mut String, state: &State, section: &Section, format: ReportFormat) {
+    match section {
+        Section::Registers => {
+            for (register, value) in state.registers.iter().enumerate() {
+                match format {
+                    ReportFormat::Hexdump => writeln!(out, "R{}: {:04X}", register, value).unwrap(),
+                    _ => writeln!(out, "R{}: {}", register, value).unwrap(),
+                }
+            }
+
+            match format {
+                ReportFormat::Hexdump => writeln!(out, "PC: {:04X}\n", state.pc).unwrap(),
+                _ => writeln!(out, "PC: {}\n", state.pc).unwrap(),
+            }
+        }
+        Section::Flags => {
+            writeln!(out, "HALT: {}", state.halt).unwrap();
+
+            if let Some(fault) = &state.fault {
+                writeln!(out, "FAULT: {}", fault).unwrap();
+            }
+
+            if let Some(exit_status) = state.exit_status {
+                writeln!(out, "EXIT STATUS: {}", exit_status).unwrap();
+            }
+
+            writeln!(out, "Flags: {:05b}\n", state.flags).unwrap();
+        }
+        Section::CallStack => {
+            writeln!(out, "Call Stack:").unwrap();
+
+            for (index, address) in call_stack(state).iter().enumerate().rev() {
+                writeln!(out, "{:04X}: {:02X}", index, address).unwrap();
+            }
+
+            writeln!(out).unwrap();
+        }
+        Section::Memory { label, range } => {
+            match (label, *range == (0..=u16::MAX)) {
+                (Some(label), _) => writeln!(
+                    out,
+                    "Memory {} ({:04X}..{:04X}):",
+                    label,
+                    range.start(),
+                    *range.end() as u32 + 1
+                )
+                .unwrap(),
+                (None, true) => writeln!(out, "Memory:").unwrap(),
+                (None, false) => {
+                    writeln!(out, "Memory {:04X}..{:04X}:", range.start(), *range.end() as u32 + 1).unwrap()
+                }
+            }
+
+            render_memory(out, state, range, format);
+            writeln!(out).unwrap();
+        }
+    }
+}
+
+/**
+ * Memory as lines of 16 words, with runs of lines repeating the line before them replaced by `*`. The first and last
+ * lines are always shown.
+ */
+fn render_memory(out: &mut String, state: &State, range: &RangeInclusive<u16>, format: ReportFormat) {
+    let words = &state.memory[*range.start() as usize..=*range.end() as usize];
+    let line_count = words.len().div_ceil(WORDS_PER_LINE);
+
+    let mut last_line: Option<&[u16]> = None;
+    let mut already_output_truncated = false;
+
+    for (index, line) in words.chunks(WORDS_PER_LINE).enumerate() {
+        if last_line == Some(line) && index != line_count - 1 {
+            if !already_output_truncated {
+                writeln!(out, "*").unwrap();
+                already_output_truncated = true;
+            }
+
+            continue;
+        }
+
+        let hex = line
+            .iter()
+            .map(|word| format!("{:04X}", word))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        write!(out, "{:04X}: {}", *range.start() as usize + index * WORDS_PER_LINE, hex).unwrap();
+
+        if format == ReportFormat::Hexdump {
+            let ascii: String = line
+                .iter()
+                .map(|word| match *word {
+                    character @ 0x20..=0x7E => character as u8 as char,
+                    _ => '.',
+                })
+                .collect();
+
+            write!(
+                out,
+                "{:width$} |{}|",
+                "",
+                ascii,
+                width = (WORDS_PER_LINE - line.len()) * 5
+            )
+            .unwrap();
+        }
+
+        writeln!(out).unwrap();
+
+        last_line = Some(line);
+        already_output_truncated = false;
+    }
+}
+
+fn call_stack(state: &State) -> &[u16] {
+    &state.call_stack[..state.call_stack_pointer as usize]
+}
+
+fn json_registers(state: &State) -> Value {
+    let mut registers: Map<String, Value> = state
+        .registers
+        .iter()
+        .enumerate()
+        .map(|(register, value)| (format!("R{}", register), json!(value)))
+        .collect();
+
+    registers.insert("PC".to_string(), json!(state.pc));
+
+    Value::Object(registers)
+}
+
+fn json_flags(state: &State) -> Value {
+    json!({
+        "flags": state.flags.as_string(),
+        "halted": state.halt,
+        "fault": state.fault,
+        "exit_status": state.exit_status,
+    })
+}
@@ -0,0 +1,486 @@
+use std::fs;
+
+use shared::BranchConditions;
+
+use crate::{
+    history::History,
+    instructions::IsaVersion,
+    interrupts::InterruptController,
+    state::{State, CALL_STACK_SIZE},
+};
+
+// Snapshot files start with this, followed by the format version and then big endian words
+const MAGIC: &[u8; 8] = b"CALSNAP\0";
+const VERSION: u16 = 2;
+
+// Changed memory ranges longer than this are summarised rather than listing every word
+const MAX_WORDS_SHOWN: usize = 8;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DeviceSnapshot {
+    pub name: String,
+    pub start: u16,
+    pub end: u16,
+    pub data: Vec<u16>,
+}
+
+/**
+ * Everything needed to resume a program: the ISA version, memory, registers, the call stack, the interrupt controller,
+ * the clock and the state of each mapped device (including input the program hasn't read yet).
+ *
+ * Files backing devices, such as disk images, aren't included. Debugger state such as breakpoints and history isn't
+ * included either.
+ */
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub isa_version: IsaVersion,
+    pub memory: Vec<u16>,
+    pub registers: [u16; 8],
+    pub pc: u16,
+    pub flags: BranchConditions,
+    pub halt: bool,
+    // The entries of the call stack below the call stack pointer
+    pub call_stack: Vec<u16>,
+    pub interrupts: InterruptController,
+    pub cycles: u64,
+    pub virtual_cycles: u64,
+    pub fault: Option<String>,
+    pub exit_status: Option<u16>,
+    pub devices: Vec<DeviceSnapshot>,
+}
+
+impl Snapshot {
+    pub fn capture(state: &State) -> Snapshot {
+        Snapshot {
+            isa_version: state.isa_version,
+            memory: state.memory.to_vec(),
+            registers: state.registers,
+            pc: state.pc,
+            flags: state.flags,
+            halt: state.halt,
+            call_stack: state.call_stack[..state.call_stack_pointer as usize].to_vec(),
+            interrupts: state.interrupts,
+            cycles: state.clock.cycles(),
+            virtual_cycles: state.clock.virtual_cycles(),
+            fault: state.fault.clone(),
+            exit_status: state.exit_status,
+            devices: state
+                .bus
+                .mappings()
+                .iter()
+                .map(|mapping| DeviceSnapshot {
+                    name: mapping.device.name().to_string(),
+                    start: *mapping.range.start(),
+                    end: *mapping.range.end(),
+                    data: mapping.device.save(),
+                })
+                .collect(),
+        }
+    }
+
+    /**
+     * Resume from the snapshot. Every device in the snapshot must be mapped at the same range in the state, devices
+     * which aren't in the snapshot are left as they are. Recorded history is discarded.
+     */
+    pub fn restore(&self, state: &mut State) -> Result<(), String> {
+        for device in &self.devices {
+            let mapping = state
+                .bus
+                .mappings_mut()
+                .iter_mut()
+                .find(|mapping| {
+                    mapping.device.name() == device.name
+                        && *mapping.range.start() == device.start
+                        && *mapping.range.end() == device.end
+                })
+                .ok_or(format!(
+                    "The snapshot has a {} at {:04X}..{:04X} which isn't mapped",
+                    device.name,
+                    device.start,
+                    device.end as u32 + 1
+                ))?;
+
+            mapping.device.restore(&device.data)?;
+        }
+
+        state.isa_version = self.isa_version;
+        state.memory.copy_from_slice(&self.memory);
+        state.invalidate_all_decoded();
+        state.registers = self.registers;
+        state.pc = self.pc;
+        state.flags = self.flags;
+        state.halt = self.halt;
+        state.call_stack[..self.call_stack.len()].copy_from_slice(&self.call_stack);
+        state.call_stack_pointer = self.call_stack.len() as u16;
+        state.interrupts = self.interrupts;
+        state.clock.set_time(self.cycles, self.virtual_cycles);
+        state.fault = self.fault.clone();
+        state.exit_status = self.exit_status;
+        state.watchpoint_hit = None;
+
+        if let Some(history) = state.history.as_mut() {
+            *history = History::new(history.limit());
+        }
+
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut words = vec![VERSION, self.isa_version as u16];
+
+        words.extend(&self.memory);
+        words.extend(self.registers);
+        words.push(self.pc);
+        words.push(self.flags.bits());
+        words.push(self.halt as u16);
+        write_words(&mut words, &self.call_stack);
+        words.push(self.interrupts.enabled as u16);
+        words.push(self.interrupts.mask);
+        words.push(self.interrupts.saved_pc);
+        words.push(self.interrupts.saved_flags.bits());
+        write_u64(&mut words, self.cycles);
+        write_u64(&mut words, self.virtual_cycles);
+
+        match &self.fault {
+            Some(fault) => {
+                words.push(1);
+                write_string(&mut words, fault);
+            }
+            None => words.push(0),
+        }
+
+        match self.exit_status {
+            Some(exit_status) => words.extend([1, exit_status]),
+            None => words.push(0),
+        }
+
+        words.push(self.devices.len() as u16);
+
+        for device in &self.devices {
+            write_string(&mut words, &device.name);
+            words.push(device.start);
+            words.push(device.end);
+            write_words(&mut words, &device.data);
+        }
+
+        MAGIC
+            .iter()
+            .copied()
+            .chain(words.iter().flat_map(|word| word.to_be_bytes()))
+            .collect()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot, String> {
+        let words = bytes
+            .strip_prefix(MAGIC)
+            .ok_or("Not a snapshot file")?
+            .chunks(2)
+            .map(|chunk| match chunk {
+                [high, low] => Ok(u16::from_be_bytes([*high, *low])),
+                _ => Err("The snapshot has a trailing byte".to_string()),
+            })
+            .collect::<Result<Vec<u16>, String>>()?;
+
+        let mut reader = Reader { words: &words };
+
+        let version = reader.word()?;
+
+        if version != VERSION {
+            return Err(format!("Unsupported snapshot version {}", version));
+        }
+
+        let isa_version = IsaVersion::try_from(reader.word()?)?;
+        let memory = reader.words(65536)?.to_vec();
+        let registers = reader.words(8)?.try_into().unwrap();
+        let pc = reader.word()?;
+        let flags = BranchConditions::from_bits_retain(reader.word()?);
+        let halt = reader.word()? != 0;
+
+        let call_stack = reader.slice()?.to_vec();
+
+        if call_stack.len() > CALL_STACK_SIZE {
+            return Err(format!("The snapshot's call stack has {} entries", call_stack.len()));
+        }
+
+        let interrupts = InterruptController {
+            enabled: reader.word()? != 0,
+            mask: reader.word()?,
+            saved_pc: reader.word()?,
+            saved_flags: BranchConditions::from_bits_retain(reader.word()?),
+        };
+
+        let cycles = reader.u64()?;
+        let virtual_cycles = reader.u64()?;
+
+        let fault = match reader.word()? {
+            0 => None,
+            _ => Some(reader.string()?),
+        };
+
+        let exit_status = match reader.word()? {
+            0 => None,
+            _ => Some(reader.word()?),
+        };
+
+        let device_count = reader.word()?;
+        let mut devices = Vec::new();
+
+        for _ in 0..device_count {
+            let name = reader.string()?;
+            let start = reader.word()?;
+            let end = reader.word()?;
+
+            devices.push(DeviceSnapshot {
+                name,
+                start,
+                end,
+                data: reader.slice()?.to_vec(),
+            });
+        }
+
+        if !reader.words.is_empty() {
+            return Err("The snapshot has trailing data".to_string());
+        }
+
+        Ok(Snapshot {
+            isa_version,
+            memory,
+            registers,
+            pc,
+            flags,
+            halt,
+            call_stack,
+            interrupts,
+            cycles,
+            virtual_cycles,
+            fault,
+            exit_status,
+            devices,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_bytes()).map_err(|e| format!("Could not write snapshot {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Snapshot, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Could not read snapshot {}: {}", path, e))?;
+
+        Snapshot::from_bytes(&bytes).map_err(|e| format!("Invalid snapshot {}: {}", path, e))
+    }
+
+    /**
+     * Describe how another snapshot differs from this one, one difference per line
+     */
+    pub fn diff(&self, other: &Snapshot) -> Vec<String> {
+        let mut differences = Vec::new();
+
+        if self.isa_version != other.isa_version {
+            differences.push(format!(
+                "ISA version: {} -> {}",
+                self.isa_version as u16, other.isa_version as u16
+            ));
+        }
+
+        for (register, (old, new)) in self.registers.iter().zip(other.registers).enumerate() {
+            if *old != new {
+                differences.push(format!("R{}: {:04X} -> {:04X}", register, old, new));
+            }
+        }
+
+        if self.pc != other.pc {
+            differences.push(format!("PC: {:04X} -> {:04X}", self.pc, other.pc));
+        }
+
+        if self.flags != other.flags {
+            differences.push(format!(
+                "Flags: {} -> {}",
+                self.flags.as_string(),
+                other.flags.as_string()
+            ));
+        }
+
+        if self.halt != other.halt {
+            differences.push(format!("Halted: {} -> {}", self.halt, other.halt));
+        }
+
+        if self.call_stack != other.call_stack {
+            differences.push(format!(
+                "Call stack: [{}] -> [{}]",
+                format_words(&self.call_stack),
+                format_words(&other.call_stack)
+            ));
+        }
+
+        if self.interrupts.enabled != other.interrupts.enabled || self.interrupts.mask != other.interrupts.mask {
+            differences.push(format!(
+                "Interrupts: {} (mask {:04X}) -> {} (mask {:04X})",
+                describe_enabled(self.interrupts.enabled),
+                self.interrupts.mask,
+                describe_enabled(other.interrupts.enabled),
+                other.interrupts.mask
+            ));
+        }
+
+        if self.cycles != other.cycles {
+            differences.push(format!("Cycles: {} -> {}", self.cycles, other.cycles));
+        }
+
+        if self.fault != other.fault {
+            differences.push(format!(
+                "Fault: {} -> {}",
+                self.fault.as_deref().unwrap_or("none"),
+                other.fault.as_deref().unwrap_or("none")
+            ));
+        }
+
+        if self.exit_status != other.exit_status {
+            differences.push(format!(
+                "Exit status: {} -> {}",
+                describe_exit_status(self.exit_status),
+                describe_exit_status(other.exit_status)
+            ));
+        }
+
+        for (start, end) in changed_ranges(&self.memory, &other.memory) {
+            let old = &self.memory[start..=end];
+            let new = &other.memory[start..=end];
+
+            differences.push(match old.len() <= MAX_WORDS_SHOWN {
+                true => format!(
+                    "Memory {:04X}..{:04X}: {} -> {}",
+                    start,
+                    end + 1,
+                    format_words(old),
+                    format_words(new)
+                ),
+                false => format!("Memory {:04X}..{:04X}: {} words changed", start, end + 1, old.len()),
+            });
+        }
+
+        for device in &self.devices {
+            let other_device = other
+                .devices
+                .iter()
+                .find(|other_device| other_device.name == device.name && other_device.start == device.start);
+
+            match other_device {
+                Some(other_device) if other_device.data != device.data => {
+                    differences.push(format!("Device {} at {:04X}: state changed", device.name, device.start))
+                }
+                Some(_) => {}
+                None => differences.push(format!("Device {} at {:04X}: removed", device.name, device.start)),
+            }
+        }
+
+        for device in &other.devices {
+            if !self
+                .devices
+                .iter()
+                .any(|old_device| old_device.name == device.name && old_device.start == device.start)
+            {
+                differences.push(format!("Device {} at {:04X}: added", device.name, device.start));
+            }
+        }
+
+        differences
+    }
+}
+
+/**
+ * The inclusive ranges of indices where two equally long slices differ
+ */
+fn changed_ranges(old: &[u16], new: &[u16]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for (index, (old, new)) in old.iter().zip(new).enumerate() {
+        if old == new {
+            continue;
+        }
+
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == index => *end = index,
+            _ => ranges.push((index, index)),
+        }
+    }
+
+    ranges
+}
+
+fn format_words(words: &[u16]) -> String {
+    words
+        .iter()
+        .map(|word| format!("{:04X}", word))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn describe_enabled(enabled: bool) -> &'static str {
+    match enabled {
+        true => "enabled",
+        false => "disabled",
+    }
+}
+
+fn describe_exit_status(exit_status: Option<u16>) -> String {
+    match exit_status {
+        Some(exit_status) => exit_status.to_string(),
+        None => "none".to_string(),
+    }
+}
+
+fn write_u64(words: &mut Vec<u16>, value: u64) {
+    words.extend((0..4).rev().map(|index| (value >> (index * 16)) as u16));
+}
+
+// Slices are written as their length (as two words) followed by their contents
+fn write_words(words: &mut Vec<u16>, values: &[u16]) {
+    words.extend([(values.len() >> 16) as u16, values.len() as u16]);
+    words.extend(values);
+}
+
+fn write_string(words: &mut Vec<u16>, string: &str) {
+    write_words(words, &string.bytes().map(|byte| byte as u16).collect::<Vec<u16>>());
+}
+
+struct Reader<'a> {
+    words: &'a [u16],
+}
+
+impl<'a> Reader<'a> {
+    fn words(&mut self, count: usize) -> Result<&'a [u16], String> {
+        if self.words.len() < count {
+            return Err("The snapshot is truncated".to_string());
+        }
+
+        let (words, rest) = self.words.split_at(count);
+
+        self.words = rest;
+
+        Ok(words)
+    }
+
+    fn word(&mut self) -> Result<u16, String> {
+        Ok(self.words(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(self
+            .words(4)?
+            .iter()
+            .fold(0, |value, word| (value << 16) | *word as u64))
+    }
+
+    // A slice written by `write_words`
+    fn slice(&mut self) -> Result<&'a [u16], String> {
+        let length = ((self.word()? as usize) << 16) | self.word()? as usize;
+
+        self.words(length)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let bytes: Vec<u8> = self.slice()?.iter().map(|word| *word as u8).collect();
+
+        String::from_utf8(bytes).map_err(|_| "The snapshot contains an invalid string".to_string())
+    }
+}
@@ -2,88 +2,260 @@ use std::fmt::{Debug, Formatter, Result as FormatResult};
 
 use shared::BranchConditions;
 
+use crate::{
+    clock::Clock,
+    coverage::Coverage,
+    debugger::{MemoryAccess, Watchpoint, WatchpointHit},
+    devices::Bus,
+    history::History,
+    instructions::{DecodedInstruction, IsaVersion, STACK_POINTER},
+    interrupts::InterruptController,
+    process::{EXIT_ADDRESS, MAX_EXIT_STATUS},
+    report::{self, ReportFormat},
+};
+
+// The number of calls the hidden call stack can hold
+pub const CALL_STACK_SIZE: usize = 256;
+
 pub struct State {
+    // Writing memory directly rather than through `write_memory` must be followed by `invalidate_decoded`
     pub memory: [u16; 65536],
-    pub call_stack: [u16; 256],
-    pub call_stack_pointer: u8,
+    pub isa_version: IsaVersion,
+    // Where CALL and RET keep return addresses in ISA version 1. From version 2 the return addresses are on the stack
+    // in memory, and this is only a best effort record of the calls for debuggers.
+    pub call_stack: [u16; CALL_STACK_SIZE],
+    pub call_stack_pointer: u16,
     pub registers: [u16; 8],
     pub pc: u16,
     pub halt: bool,
+    // Why the program was stopped, if it was stopped by an error rather than by HLT
+    pub fault: Option<String>,
+    // The value the program stored to the exit port, if it exited that way rather than with HLT
+    pub exit_status: Option<u16>,
     pub flags: BranchConditions,
-    pub stdin: Vec<u8>,
+    pub bus: Bus,
+    pub clock: Clock,
+    pub interrupts: InterruptController,
+    pub coverage: Option<Coverage>,
+    pub watchpoints: Vec<Watchpoint>,
+    // The first watchpoint triggered by the current instruction, cleared by whoever handles it
+    pub watchpoint_hit: Option<WatchpointHit>,
+    pub history: Option<History>,
+    // The decoded instruction at each address which has been executed since it was last written
+    decoded: Box<[Option<DecodedInstruction>]>,
 }
 
 impl State {
     pub fn new() -> State {
         State {
             memory: [0; 65536],
-            call_stack: [0; 256],
+            isa_version: IsaVersion::default(),
+            call_stack: [0; CALL_STACK_SIZE],
             call_stack_pointer: 0,
             registers: [0; 8],
             pc: 0,
             halt: false,
+            fault: None,
+            exit_status: None,
             flags: BranchConditions::ZERO,
-            stdin: Vec::new(),
+            bus: Bus::new(),
+            clock: Clock::default(),
+            interrupts: InterruptController::new(),
+            coverage: None,
+            watchpoints: Vec::new(),
+            watchpoint_hit: None,
+            history: None,
+            decoded: vec![None; 65536].into_boxed_slice(),
         }
     }
 
-    pub fn set_register_and_flags(&mut self, register: u8, value: u16) {
-        self.registers[register as usize] = value;
+    /**
+     * Load an assembled binary into memory and point the PC at its first instruction
+     */
+    pub fn load_binary(&mut self, bytes: &[u8]) {
+        bytes.chunks(2).enumerate().for_each(|(i, chunk)| {
+            let word = u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)]);
+            self.memory[i] = word;
+        });
 
-        if value == 0 {
-            self.flags = BranchConditions::ZERO;
-        } else if value & 0x8000 == 0x8000 {
-            self.flags = BranchConditions::NEGATIVE;
-        } else {
-            self.flags = BranchConditions::POSITIVE;
-        }
+        self.invalidate_all_decoded();
+
+        // Skip the SLT
+        self.pc = self.memory[0] + 1;
     }
-}
 
-impl Debug for State {
-    fn fmt(&self, f: &mut Formatter) -> FormatResult {
-        for i in 0..8 {
-            writeln!(f, "R{}: {}", i, self.registers[i])?;
+    /**
+     * Load a word from memory or the device mapped at the address
+     */
+    pub fn read_memory(&mut self, address: u16) -> u16 {
+        let value = match self.bus.device_at(address) {
+            Some((device, offset)) => device.load(offset),
+            None if InterruptController::contains(address) => self.interrupts.load(address, self.bus.interrupt_lines()),
+            None if address == EXIT_ADDRESS => self.exit_status.unwrap_or(0),
+            None => self.memory[address as usize],
+        };
+
+        self.check_watchpoints(address, MemoryAccess::Read, value, value);
+
+        value
+    }
+
+    /**
+     * Store a word to memory or the device mapped at the address
+     */
+    pub fn write_memory(&mut self, address: u16, value: u16) {
+        if let Some((device, offset)) = self.bus.device_at(address) {
+            device.store(offset, value);
+            self.check_watchpoints(address, MemoryAccess::Write, value, value);
+
+            return;
         }
 
-        writeln!(f, "PC: {}\n", self.pc)?;
-        writeln!(f, "HALT: {}\n", self.halt)?;
-        writeln!(f, "Call Stack:")?;
+        if InterruptController::contains(address) {
+            self.interrupts.store(address, value);
+            self.check_watchpoints(address, MemoryAccess::Write, value, value);
 
-        for i in (0..self.call_stack_pointer).rev() {
-            writeln!(f, "{:04X}: {:02X}", i, self.call_stack[i as usize])?;
+            return;
         }
 
-        writeln!(f, "\nFlags: {:03b}\n", self.flags)?;
+        if address == EXIT_ADDRESS {
+            if value > MAX_EXIT_STATUS {
+                self.raise_fault(format!(
+                    "Exit status {} is reserved, programs can exit with 0-{}",
+                    value, MAX_EXIT_STATUS
+                ));
 
-        writeln!(f, "Memory:")?;
+                return;
+            }
 
-        let mut last_line = "".to_string();
-        let mut already_output_truncated = false;
+            self.exit_status = Some(value);
+            self.halt = true;
+            self.check_watchpoints(address, MemoryAccess::Write, value, value);
 
-        for i in 0..4096 {
-            let line_output = self.memory[i * 16..i * 16 + 16]
-                .iter()
-                .map(|x| format!("{:04X}", x))
-                .collect::<Vec<String>>()
-                .join(" ");
+            return;
+        }
 
-            if line_output == last_line && i != 0 && i != 4095 {
-                if already_output_truncated {
-                    continue;
-                }
+        let old_value = self.memory[address as usize];
 
-                writeln!(f, "*")?;
-                already_output_truncated = true;
-                continue;
-            }
+        self.memory[address as usize] = value;
+        self.invalidate_decoded(address);
+
+        if let Some(history) = self.history.as_mut() {
+            history.record_memory_write(address, old_value);
+        }
+
+        self.check_watchpoints(address, MemoryAccess::Write, old_value, value);
+    }
+
+    /**
+     * The decoded instruction at an address, which is only decoded the first time it is executed after being written
+     */
+    pub fn decoded_instruction(&mut self, address: u16) -> DecodedInstruction {
+        let memory = &self.memory;
+
+        *self.decoded[address as usize].get_or_insert_with(|| DecodedInstruction::decode(memory[address as usize]))
+    }
+
+    /**
+     * Discard the cached decoding of an address so that the new contents are executed
+     */
+    pub fn invalidate_decoded(&mut self, address: u16) {
+        self.decoded[address as usize] = None;
+    }
+
+    pub fn invalidate_all_decoded(&mut self) {
+        self.decoded.fill(None);
+    }
 
-            writeln!(f, "{:04X}: {}", i * 16, line_output)?;
+    fn check_watchpoints(&mut self, address: u16, access: MemoryAccess, old_value: u16, new_value: u16) {
+        if self.watchpoints.is_empty() || self.watchpoint_hit.is_some() {
+            return;
+        }
 
-            last_line = line_output;
-            already_output_truncated = false;
+        if let Some(watchpoint) = self
+            .watchpoints
+            .iter()
+            .find(|watchpoint| watchpoint.matches(address, access))
+        {
+            self.watchpoint_hit = Some(WatchpointHit {
+                id: watchpoint.id,
+                pc: self.pc,
+                address,
+                access,
+                old_value,
+                new_value,
+            });
         }
+    }
+
+    /**
+     * Stop the program because the current instruction could not be executed
+     */
+    pub fn raise_fault(&mut self, message: String) {
+        self.fault = Some(message);
+        self.halt = true;
+    }
+
+    /**
+     * Store a word at the stack pointer, then move the stack pointer down to the next free word
+     */
+    pub fn push(&mut self, value: u16) {
+        let stack_pointer = self.registers[STACK_POINTER as usize];
+
+        self.write_memory(stack_pointer, value);
+        self.registers[STACK_POINTER as usize] = stack_pointer.wrapping_sub(1);
+    }
+
+    /**
+     * Move the stack pointer up to the last word pushed, then load it
+     */
+    pub fn pop(&mut self) -> u16 {
+        let stack_pointer = self.registers[STACK_POINTER as usize].wrapping_add(1);
+
+        self.registers[STACK_POINTER as usize] = stack_pointer;
+
+        self.read_memory(stack_pointer)
+    }
+
+    /**
+     * Set a register and the N, Z and P flags according to its value. The C and V flags are left as they were, so they
+     * survive e.g. loading the next words of a multi-word number.
+     */
+    pub fn set_register_and_flags(&mut self, register: u8, value: u16) {
+        self.registers[register as usize] = value;
+
+        let arithmetic_flags = self.flags & (BranchConditions::CARRY | BranchConditions::OVERFLOW);
+
+        if value == 0 {
+            self.flags = BranchConditions::ZERO | arithmetic_flags;
+        } else if value & 0x8000 == 0x8000 {
+            self.flags = BranchConditions::NEGATIVE | arithmetic_flags;
+        } else {
+            self.flags = BranchConditions::POSITIVE | arithmetic_flags;
+        }
+    }
+
+    /**
+     * Set a register and every flag, for the result of an addition or subtraction
+     */
+    pub fn set_register_and_arithmetic_flags(&mut self, register: u8, value: u16, carry: bool, overflow: bool) {
+        self.set_register_and_flags(register, value);
+
+        self.flags.set(BranchConditions::CARRY, carry);
+        self.flags.set(BranchConditions::OVERFLOW, overflow);
+    }
+}
+
+impl Default for State {
+    fn default() -> State {
+        State::new()
+    }
+}
+
+impl Debug for State {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        let sections = report::parse_sections(report::DEFAULT_SECTIONS, None).unwrap();
 
-        Ok(())
+        write!(f, "{}", report::render(self, &sections, ReportFormat::Human))
     }
 }
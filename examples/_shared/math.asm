@@ -1,3 +1,5 @@
+INCLUDE_ONCE "./stack.asm"
+
 // Calculate R0 divided by R1 including the remainder
 // Params:
 //   R0: Numerator
@@ -6,39 +8,13 @@
 //   R0: Result
 //   R1: Remainder
 .DIVIDE
-    // push R2
-    ST R7 #0 R2
-    SUB R7 R7 #1
-
-    // push R3
-    ST R7 #0 R3
-    SUB R7 R7 #1
-
-    LDI R2 #0
-
-.DIVIDE_LOOP
-    SUB R3 R0 R1
-    BR n .DIVIDE_END
-
-    SUB R0 R0 R1
-    ADD R2 R2 #1
-
-    BR nzp .DIVIDE_LOOP
-    
-.DIVIDE_END
-    // Set remainder
-    ADD R1 R0 #0
-
-    // Set result
-    ADD R0 R2 #0
+    PUSH R2
 
-    // pop R3
-    ADD R7 R7 #1
-    LD R3 R7 #0
+    MODU R2 R0 R1
+    DIVU R0 R0 R1
+    MOV R1 R2
 
-    // pop R2
-    ADD R7 R7 #1
-    LD R2 R7 #0
+    POP R2
 
     RET
 
@@ -49,27 +25,7 @@
 // Returns:
 //   R0: Result
 .MULTIPLY
-    PUSH R2
-    PUSH R3
-    PUSH R4
-
-    LDI R2 #0
-    LDI R3 #0
-
-.MULTIPLY_LOOP
-    SUB R4 R1 R2
-    BR nz .MULTIPLY_END
-
-    ADD R3 R3 R0
-    INC R2
-
-    BR nzp .MULTIPLY_LOOP
-
-.MULTIPLY_END
-    MOV R0 R3 // Return the result in R0
-    POP R4
-    POP R3
-    POP R2    
+    MUL R0 R0 R1
 
     RET
 
@@ -2,19 +2,14 @@ INCLUDE_ONCE "./utils.asm"
 
 .STACK_ORIGIN WORD #16383
 
-// Push a value onto the stack
-MACRO PUSH #1
-    ST R7 #0 $0
-    DEC R7
-ENDMACRO
-
-// Pop a value from the stack
-MACRO POP #1
-    INC R7
-    LD $0 R7 #0
+// Initialize the stack pointer. This is a macro rather than a subroutine since from ISA version 2 CALL and RET use the
+// stack, so it must be initialized before the first CALL.
+MACRO INIT_STACK #0
+    LOAD_VALUE_FROM_LABEL R7 .STACK_ORIGIN
 ENDMACRO
 
-// Initialize the stack pointer
+// The subroutine programs written before INIT_STACK was a macro call. It only works in ISA version 1, as from version 2
+// its return address is pushed to the stack it replaces.
 .INIT_STACK
-    LOAD_VALUE_FROM_LABEL R7 .STACK_ORIGIN
+    INIT_STACK
     RET
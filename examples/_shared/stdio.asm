@@ -31,7 +31,8 @@ INCLUDE_ONCE "./stack.asm"
 
     RET
 
-// Read a maximum number of bytes into a specified address from STDIN
+// Read a line from STDIN, including the trailing newline
+// Stops early at the end of input or after reading the maximum number of bytes
 // Params:
 //   R0: The address to read into
 //   R1: The maximum number of bytes to read including null byte
@@ -39,38 +40,43 @@ INCLUDE_ONCE "./stack.asm"
     PUSH R2
     PUSH R3
     PUSH R4
-    PUSH R5
 
-    // Null byte for comparison
-    LDI R3 #128
     LOAD_VALUE_FROM_LABEL R4 .STDIN_ADDR
 
-.STDIN_READ_WAIT_LOOP
-    SLP #10
-    LD R2 R4 #0
-    MOV R5 R2
-    SUB R2 R2 R3
-    BR z .STDIN_READ_WAIT_LOOP
-    MOV R2 R5
-    BR nzp .STDIN_READ_LOOP_INNER
+    // Set the console's blocking bit, so reads wait for input from the host instead of polling for it
+    LDI R2 #1
+    ST R4 #-1 R2
 
 .STDIN_READ_LOOP
-    // Read word from STDIN
-    LD R2 R4 #0
-
-.STDIN_READ_LOOP_INNER
     // If we have read the max amount of bytes - break
     DEC R1
     BR nz .STDIN_READ_LOOP_BREAK
 
-    // If end of stream - break
-    SUB R5 R2 R3
-    BR z .STDIN_READ_LOOP_BREAK
+.STDIN_READ_WAIT_LOOP
+    // Read a byte from STDIN, which is negative when there is none. The status is read first, so it shows whether
+    // the input had already ended
+    LD R3 R4 #-2
+    LD R2 R4 #0
+    BR zp .STDIN_READ_WAIT_END
+
+    // Input given through the debugger isn't waited for, so keep polling while the status register only has the
+    // output ready bit set, i.e. the input hasn't ended
+    LDI R2 #4
+    SUB R3 R3 R2
+    BR np .STDIN_READ_LOOP_BREAK
+    SLP #10
+    BR nzp .STDIN_READ_WAIT_LOOP
 
-    // else - store at relevant address, increment address, and continue
+.STDIN_READ_WAIT_END
+
+    // Store at relevant address and increment address
     ST R0 #0 R2
     INC R0
-    BR nzp .STDIN_READ_LOOP
+
+    // If the byte was a newline - break, else continue
+    LDI R3 #10
+    SUB R3 R2 R3
+    BR np .STDIN_READ_LOOP
 
 .STDIN_READ_LOOP_BREAK
     // Insert null byte at end
@@ -78,7 +84,6 @@ INCLUDE_ONCE "./stack.asm"
     ST R0 #0 R2
 
 .STDIN_READ_END
-    POP R5
     POP R4
     POP R3
     POP R2
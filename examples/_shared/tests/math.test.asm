@@ -0,0 +1,60 @@
+// Tests for the routines in math.asm, which are given the first two command line arguments as their operands
+// @case DIVIDE with a remainder
+// @args 17 5
+// @expect R0 = 3
+// @expect R1 = 2
+// @case DIVIDE without a remainder
+// @args 144 12
+// @expect R0 = 12
+// @expect R1 = 0
+// @case DIVIDE a smaller numerator
+// @args 3 10
+// @expect R0 = 0
+// @expect R1 = 3
+// @case MULTIPLY
+// @args 123 45
+// @expect R2 = 5535
+// @case POW
+// @args 3 4
+// @expect R3 = 81
+// @case POW with a zero exponent
+// @args 7 0
+// @expect R3 = 1
+BR nzp .MAIN
+
+INCLUDE_ONCE "../stack.asm"
+INCLUDE_ONCE "../math.asm"
+INCLUDE_ONCE "../strings.asm"
+
+.ARGUMENTS_ADDR WORD #57345
+
+// R4 and R5 = the operands, R2 = R4 * R5, R3 = R4 ^ R5, R0 and R1 = R4 / R5 and R4 % R5
+.MAIN
+    INIT_STACK
+
+    LOAD_VALUE_FROM_LABEL R6 .ARGUMENTS_ADDR
+    LD R0 R6 #0
+    CALL .ATOI
+    MOV R4 R0
+    LD R0 R6 #1
+    CALL .ATOI
+    MOV R5 R0
+
+    MOV R0 R4
+    MOV R1 R5
+    CALL .MULTIPLY
+    MOV R2 R0
+
+    MOV R0 R4
+    MOV R1 R5
+    CALL .POW
+    MOV R3 R0
+
+    // DIVIDE faults when dividing by zero
+    MOV R0 R4
+    MOV R1 R5
+    BR z .MAIN_END
+    CALL .DIVIDE
+
+.MAIN_END
+    HLT
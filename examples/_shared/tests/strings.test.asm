@@ -0,0 +1,36 @@
+// Tests for the conversions in strings.asm, which convert the first command line argument
+// @case ATOI parses a number
+// @args 1234
+// @expect R0 = 1234
+// @case ATOI parses zero
+// @args 0
+// @expect R0 = 0
+// @case ATOI returns 0 for non-numeric input
+// @args 12a4
+// @expect R0 = 0
+// @case ITOA formats the parsed number
+// @args 905
+// @expect [.ITOA_OUT] = "905"
+// @stdout 905
+BR nzp .MAIN
+
+INCLUDE_ONCE "../strings.asm"
+INCLUDE_ONCE "../stdio.asm"
+
+.FIRST_ARGUMENT_ADDR WORD #57345
+
+.MAIN
+    INIT_STACK
+
+    LOAD_VALUE_FROM_LABEL R0 .FIRST_ARGUMENT_ADDR
+    LD R0 R0 #0
+    CALL .ATOI
+
+    // Keep the parsed number in R0 for the checks
+    MOV R1 R0
+    CALL .ITOA
+    LEA R0 .ITOA_OUT
+    CALL .STDOUT_WRITE
+    MOV R0 R1
+
+    HLT
@@ -0,0 +1,39 @@
+BR nzp .MAIN
+
+INCLUDE_ONCE "../_shared/utils.asm"
+INCLUDE_ONCE "../_shared/stack.asm"
+INCLUDE_ONCE "../_shared/stdio.asm"
+
+.ARGUMENTS_ADDR WORD #57344
+.EXIT_ADDR WORD #65531
+
+// Print each command line argument on its own line, then exit with the number of arguments as the exit status
+.MAIN
+    INIT_STACK
+
+    // R1 = argc, R2 = address of the current argument's pointer
+    LOAD_VALUE_FROM_LABEL R2 .ARGUMENTS_ADDR
+    LD R1 R2 #0
+    MOV R3 R1
+    INC R2
+
+.MAIN_LOOP
+    // if remaining == 0, break
+    ADD R3 R3 #0
+    BR z .MAIN_END
+
+    LD R0 R2 #0
+    CALL .STDOUT_WRITE
+    LEA R0 .NEWLINE
+    CALL .STDOUT_WRITE
+
+    INC R2
+    DEC R3
+    BR nzp .MAIN_LOOP
+
+.MAIN_END
+    // exit(argc)
+    LOAD_VALUE_FROM_LABEL R0 .EXIT_ADDR
+    ST R0 #0 R1
+
+.NEWLINE ASCII "\n"
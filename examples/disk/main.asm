@@ -0,0 +1,39 @@
+BR nzp .MAIN
+
+INCLUDE_ONCE "../_shared/utils.asm"
+INCLUDE_ONCE "../_shared/stack.asm"
+INCLUDE_ONCE "../_shared/stdio.asm"
+
+.DISK_BUFFER_ADDR WORD #64768
+.DISK_SECTOR_ADDR WORD #65024
+
+// Print the null terminated string stored at the start of the first sector, then count the number of times the
+// program has been run in the last word of the sector
+.MAIN
+    INIT_STACK
+
+    // Read sector 0 into the transfer buffer
+    LOAD_VALUE_FROM_LABEL R1 .DISK_SECTOR_ADDR
+    LDI R0 #0
+    ST R1 #0 R0
+    LDI R0 #1
+    ST R1 #1 R0
+
+    // Stop if the read failed
+    LD R0 R1 #2
+    BR p .MAIN_END
+
+    LOAD_VALUE_FROM_LABEL R0 .DISK_BUFFER_ADDR
+    CALL .STDOUT_WRITE
+
+    // Increment the run count in the last word of the buffer
+    LD R0 R1 #-1
+    INC R0
+    ST R1 #-1 R0
+
+    // Write the transfer buffer back to sector 0
+    LDI R0 #2
+    ST R1 #1 R0
+
+.MAIN_END
+    HLT
@@ -0,0 +1,47 @@
+BR nzp .MAIN
+
+INCLUDE_ONCE "../_shared/utils.asm"
+INCLUDE_ONCE "../_shared/stack.asm"
+
+// The address of the 10th row of the display
+.DISPLAY_ROW_ADDR WORD #62160
+.DISPLAY_CURSOR_ADDR WORD #63440
+// Bright white on blue, in the attribute byte
+.ATTRIBUTE WORD #20224
+
+// Write a message to the display in colour and place the cursor after it
+.MAIN
+    INIT_STACK
+
+    LEA R0 .MESSAGE
+    LOAD_VALUE_FROM_LABEL R1 .DISPLAY_ROW_ADDR
+    LOAD_VALUE_FROM_LABEL R2 .ATTRIBUTE
+
+    // R4 = column
+    LDI R4 #0
+
+.MAIN_LOOP
+    LD R3 R0 #0
+    BR z .MAIN_END
+
+    // *display = char + attribute
+    ADD R3 R3 R2
+    ST R1 #0 R3
+
+    INC R0
+    INC R1
+    INC R4
+    BR nzp .MAIN_LOOP
+
+.MAIN_END
+    // Place the cursor after the message and show it
+    LOAD_VALUE_FROM_LABEL R1 .DISPLAY_CURSOR_ADDR
+    ST R1 #0 R4
+    LDI R0 #9
+    ST R1 #1 R0
+    LDI R0 #1
+    ST R1 #2 R0
+
+    HLT
+
+.MESSAGE ASCII "Hello from CAL!"
@@ -6,7 +6,7 @@ INCLUDE_ONCE "../_shared/strings.asm"
 INCLUDE_ONCE "../_shared/stdio.asm"
 
 .MAIN
-    CALL .INIT_STACK
+    INIT_STACK
 
     LEA R0 .STRING_1
     CALL .STDOUT_WRITE
@@ -0,0 +1,65 @@
+BR nzp .MAIN
+
+INCLUDE_ONCE "../_shared/utils.asm"
+INCLUDE_ONCE "../_shared/stack.asm"
+INCLUDE_ONCE "../_shared/stdio.asm"
+
+.VECTOR_TABLE_ADDR WORD #65280
+.INTERRUPT_MASK_ADDR WORD #65296
+.TIMER_ADDR WORD #65504
+
+// Print "tick" from a periodic timer interrupt while the main loop waits for five of them
+.MAIN
+    INIT_STACK
+
+    // vector_table[0] = .TIMER_HANDLER
+    LOAD_VALUE_FROM_LABEL R1 .VECTOR_TABLE_ADDR
+    LEA R0 .TIMER_HANDLER
+    ST R1 #0 R0
+
+    // Unmask the timer's interrupt line
+    LOAD_VALUE_FROM_LABEL R1 .INTERRUPT_MASK_ADDR
+    LDI R0 #1
+    ST R1 #0 R0
+
+    // Expire every 500 cycles
+    LOAD_VALUE_FROM_LABEL R1 .TIMER_ADDR
+    LDI R0 #500
+    ST R1 #4 R0
+
+    // Enable the countdown, make it periodic and raise an interrupt when it expires
+    LDI R0 #7
+    ST R1 #5 R0
+
+    // R5 counts the number of ticks
+    LDI R5 #0
+    EI
+
+.MAIN_WAIT_LOOP
+    LDI R0 #5
+    SUB R0 R5 R0
+    BR n .MAIN_WAIT_LOOP
+
+    DI
+    HLT
+
+.TIMER_HANDLER
+    PUSH R0
+    PUSH R1
+
+    // Acknowledge the interrupt
+    LOAD_VALUE_FROM_LABEL R1 .TIMER_ADDR
+    LDI R0 #1
+    ST R1 #6 R0
+
+    LEA R0 .TICK_STRING
+    CALL .STDOUT_WRITE
+
+    INC R5
+
+    POP R1
+    POP R0
+
+    RTI
+
+.TICK_STRING ASCII "tick\n"
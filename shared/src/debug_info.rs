@@ -0,0 +1,302 @@
+use std::{
+    fmt::{Display, Formatter, Result as FormatResult},
+    str::FromStr,
+};
+
+const HEADER: &str = "CAL-DEBUG 1";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FrameKind {
+    // The location the statement itself was written at
+    Statement,
+    // The location of a macro invocation which expanded to the statement
+    MacroInvocation,
+    // The location of an INCLUDE directive which pulled in the file containing the statement
+    Include,
+}
+
+impl FrameKind {
+    fn as_char(self) -> char {
+        match self {
+            FrameKind::Statement => 'S',
+            FrameKind::MacroInvocation => 'M',
+            FrameKind::Include => 'I',
+        }
+    }
+
+    fn from_char(character: char) -> Result<FrameKind, String> {
+        match character {
+            'S' => Ok(FrameKind::Statement),
+            'M' => Ok(FrameKind::MacroInvocation),
+            'I' => Ok(FrameKind::Include),
+            _ => Err(format!("Invalid frame kind \"{}\"", character)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SourceFrame {
+    // Index into `DebugInfo::files`
+    pub file: usize,
+    // One based line number
+    pub line: usize,
+    // One based column number
+    pub column: usize,
+    pub kind: FrameKind,
+}
+
+#[derive(Clone, Debug)]
+pub struct StatementInfo {
+    // Absolute address of the first word emitted by the statement
+    pub address: u16,
+    pub width: u16,
+    // False for directives which only emit data (e.g. WORD, ASCII and BLK)
+    pub is_instruction: bool,
+    // The source locations that produced this statement (deepest last)
+    pub backtrace: Vec<SourceFrame>,
+}
+
+impl StatementInfo {
+    pub fn contains(&self, address: u16) -> bool {
+        address >= self.address && (address as u32) < self.address as u32 + self.width as u32
+    }
+
+    // The location the statement was written at
+    pub fn location(&self) -> &SourceFrame {
+        self.backtrace.last().unwrap()
+    }
+
+    // The outermost location which isn't an include, i.e. the line which produced this statement when macro
+    // expansions are collapsed
+    pub fn outermost_location(&self) -> &SourceFrame {
+        self.backtrace
+            .iter()
+            .find(|frame| frame.kind != FrameKind::Include)
+            .unwrap()
+    }
+}
+
+/**
+ * Maps addresses in an assembled binary back to the source that produced them
+ */
+#[derive(Clone, Debug, Default)]
+pub struct DebugInfo {
+    pub files: Vec<String>,
+    // Absolute label addresses, sorted by address
+    pub labels: Vec<(String, u16)>,
+    // Sorted by address
+    pub statements: Vec<StatementInfo>,
+}
+
+impl DebugInfo {
+    pub fn new() -> DebugInfo {
+        DebugInfo::default()
+    }
+
+    pub fn file_index(&mut self, file: &str) -> usize {
+        match self.files.iter().position(|existing| existing == file) {
+            Some(index) => index,
+            None => {
+                self.files.push(file.to_owned());
+                self.files.len() - 1
+            }
+        }
+    }
+
+    pub fn statement_at(&self, address: u16) -> Option<&StatementInfo> {
+        let index = self
+            .statements
+            .partition_point(|statement| statement.address <= address);
+
+        match index {
+            0 => None,
+            _ => Some(&self.statements[index - 1]).filter(|statement| statement.contains(address)),
+        }
+    }
+
+    pub fn label_address(&self, name: &str) -> Option<u16> {
+        self.labels
+            .iter()
+            .find(|(label, _)| label == name)
+            .map(|(_, address)| *address)
+    }
+
+    /**
+     * Find a file by path, matching either the full path or any trailing path components (e.g. `stdio.asm` or
+     * `_shared/stdio.asm`)
+     */
+    pub fn find_file(&self, path: &str) -> Option<usize> {
+        self.files
+            .iter()
+            .position(|file| file == path || (file.ends_with(path) && file[..file.len() - path.len()].ends_with('/')))
+    }
+
+    /**
+     * The address of the first instruction assembled from a source line, including lines invoking macros
+     */
+    pub fn line_address(&self, file: usize, line: usize) -> Option<u16> {
+        self.statements
+            .iter()
+            .filter(|statement| statement.is_instruction)
+            .find(|statement| {
+                statement
+                    .backtrace
+                    .iter()
+                    .any(|frame| frame.file == file && frame.line == line && frame.kind != FrameKind::Include)
+            })
+            .map(|statement| statement.address)
+    }
+
+    // The closest label at or before the given address
+    pub fn label_before(&self, address: u16) -> Option<&(String, u16)> {
+        self.labels
+            .iter()
+            .rev()
+            .find(|(_, label_address)| *label_address <= address)
+    }
+}
+
+impl Display for DebugInfo {
+    fn fmt(&self, f: &mut Formatter) -> FormatResult {
+        writeln!(f, "{}", HEADER)?;
+
+        for (index, file) in self.files.iter().enumerate() {
+            writeln!(f, "FILE {} {}", index, file)?;
+        }
+
+        for (label, address) in &self.labels {
+            writeln!(f, "LABEL {:04X} {}", address, label)?;
+        }
+
+        for statement in &self.statements {
+            write!(
+                f,
+                "STATEMENT {:04X} {} {}",
+                statement.address,
+                statement.width,
+                if statement.is_instruction { "I" } else { "D" }
+            )?;
+
+            for frame in &statement.backtrace {
+                write!(
+                    f,
+                    " {}:{}:{}:{}",
+                    frame.file,
+                    frame.line,
+                    frame.column,
+                    frame.kind.as_char()
+                )?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for DebugInfo {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<DebugInfo, String> {
+        let mut lines = source.lines().enumerate();
+
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            _ => return Err(format!("Missing debug info header \"{}\"", HEADER)),
+        }
+
+        let mut debug_info = DebugInfo::new();
+
+        for (line_index, line) in lines {
+            parse_line(line, &mut debug_info).map_err(|e| format!("Line {}: {}", line_index + 1, e))?;
+        }
+
+        debug_info.labels.sort_by_key(|(_, address)| *address);
+        debug_info.statements.sort_by_key(|statement| statement.address);
+
+        Ok(debug_info)
+    }
+}
+
+fn parse_line(line: &str, debug_info: &mut DebugInfo) -> Result<(), String> {
+    let mut parts = line.splitn(3, ' ');
+
+    match parts.next() {
+        Some("FILE") => {
+            let index = parse_number(parts.next(), 10)?;
+
+            if index != debug_info.files.len() {
+                return Err(format!(
+                    "Expected file index {}, found {}",
+                    debug_info.files.len(),
+                    index
+                ));
+            }
+
+            debug_info
+                .files
+                .push(parts.next().ok_or("Missing file path")?.to_owned());
+        }
+        Some("LABEL") => {
+            let address = parse_number(parts.next(), 16)? as u16;
+            let label = parts.next().ok_or("Missing label name")?;
+
+            debug_info.labels.push((label.to_owned(), address));
+        }
+        Some("STATEMENT") => {
+            let mut fields = line.split(' ').skip(1);
+
+            let address = parse_number(fields.next(), 16)? as u16;
+            let width = parse_number(fields.next(), 10)? as u16;
+            let is_instruction = match fields.next() {
+                Some("I") => true,
+                Some("D") => false,
+                _ => return Err("Invalid statement kind".to_string()),
+            };
+
+            let backtrace = fields.map(parse_frame).collect::<Result<Vec<SourceFrame>, String>>()?;
+
+            if backtrace.is_empty() {
+                return Err(format!("Statement at {:04X} has no source location", address));
+            }
+
+            debug_info.statements.push(StatementInfo {
+                address,
+                width,
+                is_instruction,
+                backtrace,
+            });
+        }
+        Some("") => {}
+        Some(other) => return Err(format!("Unrecognized debug info entry \"{}\"", other)),
+        None => {}
+    }
+
+    Ok(())
+}
+
+fn parse_frame(frame: &str) -> Result<SourceFrame, String> {
+    let mut fields = frame.split(':');
+
+    let file = parse_number(fields.next(), 10)?;
+    let line = parse_number(fields.next(), 10)?;
+    let column = parse_number(fields.next(), 10)?;
+    let kind = match fields.next().map(|kind| kind.chars().collect::<Vec<char>>()).as_deref() {
+        Some([character]) => FrameKind::from_char(*character)?,
+        _ => return Err(format!("Invalid source frame \"{}\"", frame)),
+    };
+
+    Ok(SourceFrame {
+        file,
+        line,
+        column,
+        kind,
+    })
+}
+
+fn parse_number(field: Option<&str>, radix: u32) -> Result<usize, String> {
+    let field = field.ok_or("Unexpected end of line")?;
+
+    usize::from_str_radix(field, radix).map_err(|_| format!("Invalid number \"{}\"", field))
+}
@@ -1,9 +1,17 @@
 use bitflags::bitflags;
+
+pub mod debug_info;
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct BranchConditions(u16);
 
 bitflags! {
     impl BranchConditions: u16 {
+        // Set by ADD and SUB when the result doesn't fit in 16 bits as an unsigned number (a carry out of, or a borrow
+        // into, the top bit)
+        const CARRY    = 1 << 3;
+        // Set by ADD and SUB when the result doesn't fit in 16 bits as a signed number
+        const OVERFLOW = 1 << 4;
         const NEGATIVE = 1 << 2;
         const ZERO     = 1 << 1;
         const POSITIVE = 1 << 0;
@@ -12,10 +20,12 @@ bitflags! {
 
 impl BranchConditions {
     pub fn as_string(self) -> String {
-        vec![
+        [
             if (self & Self::NEGATIVE).bits() != 0 { "n" } else { "" },
             if (self & Self::ZERO).bits() != 0 { "z" } else { "" },
             if (self & Self::POSITIVE).bits() != 0 { "p" } else { "" },
+            if (self & Self::CARRY).bits() != 0 { "c" } else { "" },
+            if (self & Self::OVERFLOW).bits() != 0 { "v" } else { "" },
         ]
         .join("")
     }